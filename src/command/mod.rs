@@ -0,0 +1,210 @@
+use crate::tile;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// percent of the parent's space resizing moves by when no amount is given
+const DEFAULT_RESIZE: u8 = 5;
+
+// Everything the user can ask the window manager to do. Commands are written as plain text
+// (in the config file, in traces) and parsed with `str::parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    ToggleOrientation,
+    LoadTemplate(PathBuf),
+    ClearPlaceholders,
+    SetInsertion(tile::Insertion),
+    // side of the focused window the next window goes to, and the share of space it gets
+    Preselect(tile::Direction, f32),
+    CancelPreselection,
+    // flips the focused window's container between side by side and stacked
+    ToggleSplit,
+    Rotate(tile::Rotation),
+    Mirror(tile::Orientation),
+    Balance,
+    // evens out the focused window's container only
+    Equalize,
+    ToggleAutoBalance,
+    // shows the focused window over the whole monitor
+    ToggleFullscreen,
+    // shows the focused window over the rest of the workspace
+    ToggleZoom,
+    // takes the focused window out of the tree and hides it
+    MoveToScratchpad,
+    // shows or hides a scratchpad window, the named one if given
+    ToggleScratchpad(Option<String>),
+    // the window focused before the current one
+    FocusLast,
+    // the windows after and before the focused one, in tree order
+    FocusNext,
+    FocusPrev,
+    // selects the container around the selection, or back down towards the focused window
+    FocusParent,
+    FocusChild,
+    // the commands below act on the selected container, or the focused window
+    Move(tile::Direction),
+    // change in the share of the parent's space, negative to shrink
+    Resize(f32),
+    ToggleFloating,
+    SendToWorkspace(String),
+    SwitchWorkspace(String),
+    // gives back every hidden window and exits
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Command, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["orientation", "toggle"] => Ok(Command::ToggleOrientation),
+            ["template", "load", ..] if words.len() > 2 => {
+                // paths can contain spaces, so take everything after the second word as-is
+                let path = s.trim().splitn(3, char::is_whitespace).nth(2).unwrap_or_default().trim();
+                Ok(Command::LoadTemplate(PathBuf::from(path)))
+            }
+            ["template", "clear"] => Ok(Command::ClearPlaceholders),
+            ["insert", policy] => Ok(Command::SetInsertion(policy.parse()?)),
+            ["presel", "cancel"] => Ok(Command::CancelPreselection),
+            ["presel", direction] => Ok(Command::Preselect(direction.parse()?, tile::DEFAULT_RATIO)),
+            ["presel", direction, ratio] => {
+                let ratio = ratio
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|ratio| *ratio > 0.0 && *ratio < 1.0)
+                    .ok_or_else(|| format!("ratio '{}' is not between 0 and 1", ratio))?;
+                Ok(Command::Preselect(direction.parse()?, ratio))
+            }
+            ["split", "toggle"] => Ok(Command::ToggleSplit),
+            ["rotate", degrees] => Ok(Command::Rotate(degrees.parse()?)),
+            ["mirror", "horizontal"] => Ok(Command::Mirror(tile::Orientation::Horizontal)),
+            ["mirror", "vertical"] => Ok(Command::Mirror(tile::Orientation::Vertical)),
+            ["balance"] => Ok(Command::Balance),
+            ["equalize"] => Ok(Command::Equalize),
+            ["balance", "auto"] => Ok(Command::ToggleAutoBalance),
+            ["fullscreen"] => Ok(Command::ToggleFullscreen),
+            ["zoom"] => Ok(Command::ToggleZoom),
+            ["scratchpad", "move"] => Ok(Command::MoveToScratchpad),
+            ["scratchpad", "show"] => Ok(Command::ToggleScratchpad(None)),
+            ["scratchpad", "show", name] => Ok(Command::ToggleScratchpad(Some(name.to_string()))),
+            ["focus", "last"] => Ok(Command::FocusLast),
+            ["focus", "next"] => Ok(Command::FocusNext),
+            ["focus", "prev"] => Ok(Command::FocusPrev),
+            ["focus", "parent"] => Ok(Command::FocusParent),
+            ["focus", "child"] => Ok(Command::FocusChild),
+            ["move", direction] => Ok(Command::Move(direction.parse()?)),
+            ["resize", "grow" | "shrink", ..] if words.len() <= 3 => {
+                let percent = match words.get(2) {
+                    Some(percent) => percent
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|percent| *percent <= 100)
+                        .ok_or_else(|| format!("'{}' is not a percentage", percent))?,
+                    None => DEFAULT_RESIZE,
+                };
+                let change = percent as f32 / 100.0;
+                Ok(Command::Resize(if words[1] == "grow" { change } else { -change }))
+            }
+            ["float", "toggle"] => Ok(Command::ToggleFloating),
+            ["send", name] => Ok(Command::SendToWorkspace(name.to_string())),
+            ["workspace", name] => Ok(Command::SwitchWorkspace(name.to_string())),
+            ["quit"] => Ok(Command::Quit),
+            [] => Err("empty command".to_string()),
+            _ => Err(format!("unknown command '{}'", s.trim())),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::ToggleOrientation => write!(f, "orientation toggle"),
+            Command::LoadTemplate(path) => write!(f, "template load {}", path.display()),
+            Command::ClearPlaceholders => write!(f, "template clear"),
+            Command::SetInsertion(insertion) => write!(f, "insert {}", insertion),
+            Command::Preselect(direction, ratio) => write!(f, "presel {} {}", direction, ratio),
+            Command::CancelPreselection => write!(f, "presel cancel"),
+            Command::ToggleSplit => write!(f, "split toggle"),
+            Command::Rotate(rotation) => write!(f, "rotate {}", rotation),
+            Command::Mirror(tile::Orientation::Horizontal) => write!(f, "mirror horizontal"),
+            Command::Mirror(tile::Orientation::Vertical) => write!(f, "mirror vertical"),
+            Command::Balance => write!(f, "balance"),
+            Command::Equalize => write!(f, "equalize"),
+            Command::ToggleAutoBalance => write!(f, "balance auto"),
+            Command::ToggleFullscreen => write!(f, "fullscreen"),
+            Command::ToggleZoom => write!(f, "zoom"),
+            Command::MoveToScratchpad => write!(f, "scratchpad move"),
+            Command::ToggleScratchpad(None) => write!(f, "scratchpad show"),
+            Command::ToggleScratchpad(Some(name)) => write!(f, "scratchpad show {}", name),
+            Command::FocusLast => write!(f, "focus last"),
+            Command::FocusNext => write!(f, "focus next"),
+            Command::FocusPrev => write!(f, "focus prev"),
+            Command::FocusParent => write!(f, "focus parent"),
+            Command::FocusChild => write!(f, "focus child"),
+            Command::Move(direction) => write!(f, "move {}", direction),
+            Command::Resize(change) if *change < 0.0 => write!(f, "resize shrink {}", (-change * 100.0).round()),
+            Command::Resize(change) => write!(f, "resize grow {}", (change * 100.0).round()),
+            Command::ToggleFloating => write!(f, "float toggle"),
+            Command::SendToWorkspace(name) => write!(f, "send {}", name),
+            Command::SwitchWorkspace(name) => write!(f, "workspace {}", name),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_should_round_trip_with_display() {
+        let commands = vec![
+            Command::ToggleOrientation,
+            Command::LoadTemplate(PathBuf::from("C:\\Users\\me\\My Layouts\\dev.json")),
+            Command::ClearPlaceholders,
+            Command::SetInsertion(tile::Insertion::Auto),
+            Command::Preselect(tile::Direction::Left, 0.3),
+            Command::CancelPreselection,
+            Command::ToggleSplit,
+            Command::Rotate(tile::Rotation::ThreeQuarters),
+            Command::Mirror(tile::Orientation::Vertical),
+            Command::Balance,
+            Command::Equalize,
+            Command::ToggleAutoBalance,
+            Command::ToggleFullscreen,
+            Command::ToggleZoom,
+            Command::MoveToScratchpad,
+            Command::ToggleScratchpad(None),
+            Command::ToggleScratchpad(Some("term".to_string())),
+            Command::FocusLast,
+            Command::FocusNext,
+            Command::FocusPrev,
+            Command::FocusParent,
+            Command::FocusChild,
+            Command::Move(tile::Direction::Up),
+            Command::Resize(-0.1),
+            Command::Resize(0.05),
+            Command::ToggleFloating,
+            Command::SendToWorkspace("2".to_string()),
+            Command::SwitchWorkspace("mail".to_string()),
+            Command::Quit,
+        ];
+
+        for command in commands {
+            assert_eq!(Ok(command.clone()), command.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn parse_should_reject_unknown_commands() {
+        assert!("orientation sideways".parse::<Command>().is_err());
+        assert!("template load".parse::<Command>().is_err());
+        assert!("presel left 1.5".parse::<Command>().is_err());
+        assert!("presel sideways".parse::<Command>().is_err());
+        assert!("rotate 45".parse::<Command>().is_err());
+        assert!("resize grow 150".parse::<Command>().is_err());
+        assert_eq!(Ok(Command::Resize(0.05)), "resize grow".parse());
+        assert!("".parse::<Command>().is_err());
+    }
+}
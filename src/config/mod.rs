@@ -0,0 +1,462 @@
+use crate::command::Command;
+use crate::internal::Criteria;
+use crate::monitor::{Edges, Reservation};
+use crate::scratchpad::{self, NamedScratchpad};
+use crate::tile;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// The config file is line based:
+//
+//   # comment
+//   bind alt+x orientation toggle
+//   set insertion auto
+//   set focus_follows_mouse 200
+//   set mouse_follows_focus on
+//   set gaps 8
+//   set overflow float
+//   set enforce_layout on
+//   set coalesce 20
+//   scratchpad term process=wt.exe wt.exe
+//   reserve 1 top=30
+//   reserve \\.\DISPLAY2 left=40
+//
+// Bindings from the file are added to the default ones, binding the same keys again
+// replaces the default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub bindings: Vec<Binding>,
+    pub insertion: tile::Insertion,
+    pub scratchpads: Vec<NamedScratchpad>,
+    // how long the cursor has to rest on a window before it gets focus, None turns it off
+    pub focus_follows_mouse: Option<Duration>,
+    pub mouse_follows_focus: bool,
+    pub reservations: Vec<Reservation>,
+    // space around tiled windows, in logical pixels
+    pub gaps: i32,
+    // what happens when windows' minimum sizes don't fit next to each other
+    pub overflow: tile::Overflow,
+    // put windows back whenever they move themselves, not only right after placing them
+    pub enforce_layout: bool,
+    // how long window events are held back to let bursts of them cancel out
+    pub coalesce: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub keys: Keys,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keys {
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub win: bool,
+    pub key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Function(u8),
+    Space,
+    Enter,
+    Tab,
+    Escape,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let alt = |c| Keys { alt: true, ctrl: false, shift: false, win: false, key: Key::Char(c) };
+        Config {
+            bindings: vec![
+                Binding { keys: alt('X'), command: Command::ToggleOrientation },
+                Binding { keys: alt('C'), command: Command::ToggleOrientation },
+            ],
+            insertion: tile::Insertion::SplitFocused,
+            scratchpads: Vec::new(),
+            focus_follows_mouse: None,
+            mouse_follows_focus: false,
+            reservations: Vec::new(),
+            gaps: 0,
+            overflow: tile::Overflow::Shrink,
+            enforce_layout: false,
+            coalesce: Duration::from_millis(20),
+        }
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("tiles10").join("config"))
+}
+
+impl Config {
+    // A missing file just means the defaults are used.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (number, line) in contents.lines().enumerate() {
+            config
+                .parse_line(line.trim())
+                .map_err(|e| format!("config line {}: {}", number + 1, e))?;
+        }
+        Ok(config)
+    }
+
+    // The lines of a config file that give back everything but the bindings, which only ever
+    // turn into commands.
+    pub fn settings(&self) -> Vec<String> {
+        let switch = |on: bool| if on { "on" } else { "off" };
+        let mut lines = vec![
+            format!("set insertion {}", self.insertion),
+            match self.focus_follows_mouse {
+                Some(delay) => format!("set focus_follows_mouse {}", delay.as_millis()),
+                None => "set focus_follows_mouse off".to_string(),
+            },
+            format!("set mouse_follows_focus {}", switch(self.mouse_follows_focus)),
+            format!("set gaps {}", self.gaps),
+            format!("set overflow {}", self.overflow),
+            format!("set enforce_layout {}", switch(self.enforce_layout)),
+            format!("set coalesce {}", self.coalesce.as_millis()),
+        ];
+        for scratchpad in &self.scratchpads {
+            let criteria = &scratchpad.criteria;
+            let mut words = vec![format!("scratchpad {}", scratchpad.name)];
+            words.extend(criteria.process.iter().map(|process| format!("process={}", quoted(process))));
+            words.extend(criteria.class.iter().map(|class| format!("class={}", quoted(class))));
+            words.extend(criteria.title.iter().map(|title| format!("title={}", quoted(title))));
+            words.push(scratchpad.program.clone());
+            lines.push(words.join(" "));
+        }
+        for reservation in &self.reservations {
+            let edges = &reservation.edges;
+            lines.push(format!(
+                "reserve {} top={} bottom={} left={} right={}",
+                reservation.monitor, edges.top, edges.bottom, edges.left, edges.right
+            ));
+        }
+        lines
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let mut words = line.splitn(2, char::is_whitespace);
+        let directive = words.next().unwrap_or_default();
+        let rest = words.next().unwrap_or_default().trim();
+        match directive {
+            "bind" => {
+                let mut words = rest.splitn(2, char::is_whitespace);
+                let keys = parse_keys(words.next().unwrap_or_default())?;
+                let command = words.next().unwrap_or_default().parse()?;
+                self.bindings.retain(|binding| binding.keys != keys);
+                self.bindings.push(Binding { keys, command });
+                Ok(())
+            }
+            "set" => {
+                let mut words = rest.split_whitespace();
+                match (words.next(), words.next(), words.next()) {
+                    (Some("insertion"), Some(value), None) => self.insertion = value.parse()?,
+                    (Some("focus_follows_mouse"), Some("off"), None) => self.focus_follows_mouse = None,
+                    (Some("focus_follows_mouse"), Some(value), None) => match value.parse() {
+                        Ok(ms) => self.focus_follows_mouse = Some(Duration::from_millis(ms)),
+                        Err(_) => return Err(format!("'{}' is not a delay in milliseconds or off", value)),
+                    },
+                    (Some("coalesce"), Some(value), None) => match value.parse() {
+                        Ok(ms) => self.coalesce = Duration::from_millis(ms),
+                        Err(_) => return Err(format!("'{}' is not a delay in milliseconds", value)),
+                    },
+                    (Some("gaps"), Some(value), None) => match value.parse() {
+                        Ok(gaps) if gaps >= 0 => self.gaps = gaps,
+                        _ => return Err(format!("'{}' is not a number of pixels", value)),
+                    },
+                    (Some("overflow"), Some(value), None) => self.overflow = value.parse()?,
+                    (Some("enforce_layout"), Some(value), None) => self.enforce_layout = parse_switch(value)?,
+                    (Some("mouse_follows_focus"), Some(value), None) => self.mouse_follows_focus = parse_switch(value)?,
+                    (Some(name), _, _) => return Err(format!("cannot set '{}'", name)),
+                    (None, _, _) => return Err("set needs a name and a value".to_string()),
+                }
+                Ok(())
+            }
+            "scratchpad" => {
+                let scratchpad = parse_scratchpad(rest)?;
+                self.scratchpads.retain(|existing| existing.name != scratchpad.name);
+                self.scratchpads.push(scratchpad);
+                Ok(())
+            }
+            "reserve" => {
+                let reservation = parse_reservation(rest)?;
+                self.reservations.retain(|existing| existing.monitor != reservation.monitor);
+                self.reservations.push(reservation);
+                Ok(())
+            }
+            _ => Err(format!("unknown directive '{}'", directive)),
+        }
+    }
+}
+
+// A value as it has to be written for split_words to read it back.
+fn quoted(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off, found '{}'", value)),
+    }
+}
+
+// Parses "<name> <key>=<value>... <command line>", where the keys are process, class and title.
+// Values with spaces go in double quotes, `title="My App"`.
+fn parse_scratchpad(value: &str) -> Result<NamedScratchpad, String> {
+    let mut words = scratchpad::split_words(value).into_iter();
+    let name = words.next().map(|(_, name)| name).ok_or_else(|| "scratchpad needs a name".to_string())?;
+
+    let mut criteria = Criteria::default();
+    let mut program = "";
+    for (start, word) in words {
+        // criteria come first, everything after them is the command line, kept as written so
+        // quoted paths reach the launch intact
+        match word.split_once('=') {
+            Some(("process", value)) => criteria.process = Some(value.to_string()),
+            Some(("class", value)) => criteria.class = Some(value.to_string()),
+            Some(("title", value)) => criteria.title = Some(value.to_string()),
+            _ => {
+                program = value[start..].trim();
+                break;
+            }
+        }
+    }
+
+    if criteria == Criteria::default() {
+        return Err(format!("scratchpad '{}' needs process=, class= or title= to find its window", name));
+    }
+    if program.is_empty() {
+        return Err(format!("scratchpad '{}' needs a program to launch", name));
+    }
+    Ok(NamedScratchpad {
+        name,
+        criteria,
+        program: program.to_string(),
+    })
+}
+
+// Parses "<monitor> <edge>=<pixels>...", where the edges are top, bottom, left and right.
+fn parse_reservation(value: &str) -> Result<Reservation, String> {
+    let mut words = value.split_whitespace();
+    // a number counts monitors from 1, anything else is a monitor's id
+    let monitor = match words.next() {
+        Some(word) if !word.contains('=') && word.parse::<usize>().map_or(true, |number| number > 0) => {
+            word.to_string()
+        }
+        _ => return Err("reserve needs a monitor id or number, starting at 1".to_string()),
+    };
+
+    let mut edges = Edges::default();
+    for word in words {
+        let (edge, pixels) = word
+            .split_once('=')
+            .ok_or_else(|| format!("expected <edge>=<pixels>, found '{}'", word))?;
+        let pixels = pixels
+            .parse()
+            .map_err(|_| format!("'{}' is not a number of pixels", pixels))?;
+        match edge {
+            "top" => edges.top = pixels,
+            "bottom" => edges.bottom = pixels,
+            "left" => edges.left = pixels,
+            "right" => edges.right = pixels,
+            _ => return Err(format!("unknown edge '{}'", edge)),
+        }
+    }
+    Ok(Reservation { monitor, edges })
+}
+
+// Parses a key combination such as "alt+shift+x" or "win+f1".
+pub fn parse_keys(value: &str) -> Result<Keys, String> {
+    let mut keys = Keys { alt: false, ctrl: false, shift: false, win: false, key: Key::Space };
+    let mut key = None;
+
+    for part in value.split('+') {
+        match part.to_lowercase().as_str() {
+            "alt" => keys.alt = true,
+            "ctrl" => keys.ctrl = true,
+            "shift" => keys.shift = true,
+            "win" => keys.win = true,
+            name => {
+                if key.is_some() {
+                    return Err(format!("'{}' has more than one key", value));
+                }
+                key = Some(parse_key(name).ok_or_else(|| format!("unknown key '{}'", part))?);
+            }
+        }
+    }
+
+    match key {
+        Some(key) => {
+            keys.key = key;
+            Ok(keys)
+        }
+        None => Err(format!("'{}' has no key", value)),
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "space" => Key::Space,
+        "enter" => Key::Enter,
+        "tab" => Key::Tab,
+        "escape" => Key::Escape,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => Key::Char(c.to_ascii_uppercase()),
+                (Some('f'), Some(_)) => match name[1..].parse::<u8>() {
+                    Ok(n) if (1..=24).contains(&n) => Key::Function(n),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_keys_should_read_modifiers_and_key() {
+        let keys = parse_keys("ctrl+Shift+f5").unwrap();
+
+        assert_eq!(Keys { alt: false, ctrl: true, shift: true, win: false, key: Key::Function(5) }, keys);
+        assert_eq!(Key::Char('T'), parse_keys("win+t").unwrap().key);
+        assert_eq!(Key::Left, parse_keys("alt+left").unwrap().key);
+    }
+
+    #[test]
+    fn parse_keys_should_reject_bad_combinations() {
+        assert!(parse_keys("alt+shift").is_err());
+        assert!(parse_keys("alt+x+y").is_err());
+        assert!(parse_keys("alt+f25").is_err());
+        assert!(parse_keys("alt+pagedown").is_err());
+    }
+
+    #[test]
+    fn settings_should_parse_back_to_same_config() {
+        let config = Config::parse("\
+set insertion auto
+set focus_follows_mouse 200
+set mouse_follows_focus on
+set gaps 8
+set overflow float
+set coalesce 0
+scratchpad term process=wt.exe class=CASCADIA_HOSTING_WINDOW_CLASS wt.exe -p cmd
+scratchpad notes title=\"My Notes\" \"C:\\Program Files\\notes.exe\"
+reserve 2 top=30 left=5
+").unwrap();
+
+        assert_eq!(config, Config::parse(&config.settings().join("\n")).unwrap());
+        assert_eq!(Config::default(), Config::parse(&Config::default().settings().join("\n")).unwrap());
+    }
+
+    #[test]
+    fn parse_should_add_bindings_and_replace_defaults_with_same_keys() {
+        let config = Config::parse("# layouts\nbind alt+x template clear\n\nbind alt+t template load dev.json\n").unwrap();
+
+        assert_eq!(3, config.bindings.len());
+        let alt_x = config.bindings.iter().find(|b| b.keys == parse_keys("alt+x").unwrap()).unwrap();
+        assert_eq!(Command::ClearPlaceholders, alt_x.command);
+        let alt_t = config.bindings.iter().find(|b| b.keys == parse_keys("alt+t").unwrap()).unwrap();
+        assert_eq!(Command::LoadTemplate(PathBuf::from("dev.json")), alt_t.command);
+    }
+
+    #[test]
+    fn parse_should_set_options() {
+        let config = Config::parse("set insertion parent\nset focus_follows_mouse 150\nset mouse_follows_focus on").unwrap();
+
+        assert_eq!(tile::Insertion::Parent, config.insertion);
+        assert_eq!(Some(Duration::from_millis(150)), config.focus_follows_mouse);
+        assert!(config.mouse_follows_focus);
+        assert_eq!(None, Config::parse("set focus_follows_mouse off").unwrap().focus_follows_mouse);
+        assert!(Config::parse("set insertion sideways").is_err());
+        assert!(Config::parse("set focus_follows_mouse soon").is_err());
+        assert!(Config::parse("set mouse_follows_focus yes").is_err());
+        assert_eq!(8, Config::parse("set gaps 8").unwrap().gaps);
+        assert!(Config::parse("set gaps -8").is_err());
+        assert_eq!(tile::Overflow::Float, Config::parse("set overflow float").unwrap().overflow);
+        assert!(Config::parse("set overflow wrap").is_err());
+        assert!(Config::parse("set enforce_layout on").unwrap().enforce_layout);
+        assert_eq!(Duration::ZERO, Config::parse("set coalesce 0").unwrap().coalesce);
+        assert!(Config::parse("set coalesce soon").is_err());
+        assert!(Config::parse("set opacity 90").is_err());
+    }
+
+    #[test]
+    fn parse_should_read_named_scratchpads() {
+        let config = Config::parse("scratchpad music process=spotify.exe title=Spotify spotify.exe --minimized").unwrap();
+
+        assert_eq!(1, config.scratchpads.len());
+        let scratchpad = &config.scratchpads[0];
+        assert_eq!("music", scratchpad.name);
+        assert_eq!(Some("spotify.exe".to_string()), scratchpad.criteria.process);
+        assert_eq!(Some("Spotify".to_string()), scratchpad.criteria.title);
+        assert_eq!("spotify.exe --minimized", scratchpad.program);
+
+        let config = Config::parse(r#"scratchpad notes title="My Notes" "C:\Program Files\Notes\notes.exe" --new"#).unwrap();
+        assert_eq!(Some("My Notes".to_string()), config.scratchpads[0].criteria.title);
+        assert_eq!(r#""C:\Program Files\Notes\notes.exe" --new"#, config.scratchpads[0].program);
+        assert!(Config::parse("scratchpad music spotify.exe").is_err());
+        assert!(Config::parse("scratchpad music process=spotify.exe").is_err());
+    }
+
+    #[test]
+    fn parse_should_read_reserved_edges_per_monitor() {
+        let config = Config::parse("reserve 1 top=30\nreserve 2 left=40 right=40\nreserve 1 bottom=20\nreserve \\\\.\\DISPLAY3 top=10").unwrap();
+
+        assert_eq!(3, config.reservations.len());
+        assert_eq!(
+            Reservation { monitor: "2".to_string(), edges: Edges { top: 0, bottom: 0, left: 40, right: 40 } },
+            config.reservations[0]
+        );
+        assert_eq!(Edges { bottom: 20, ..Default::default() }, config.reservations[1].edges);
+        assert_eq!("\\\\.\\DISPLAY3", config.reservations[2].monitor);
+        assert!(Config::parse("reserve 0 top=30").is_err());
+        assert!(Config::parse("reserve top=30").is_err());
+        assert!(Config::parse("reserve 1 middle=30").is_err());
+        assert!(Config::parse("reserve 1 top=lots").is_err());
+    }
+
+    #[test]
+    fn parse_should_report_line_of_invalid_directive() {
+        let err = Config::parse("bind alt+x orientation toggle\nbindsym alt+y template clear").unwrap_err();
+
+        assert!(err.contains("config line 2"));
+    }
+}
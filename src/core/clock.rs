@@ -0,0 +1,37 @@
+use std::time::Instant;
+
+// Where the time comes from, so anything that waits can be tested without sleeping.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// A clock that only moves when it's told to, for tests and for replaying a trace at the pace it
+// was recorded. Clones share the same time.
+#[derive(Clone)]
+pub struct FakeClock(std::rc::Rc<std::cell::Cell<Instant>>);
+
+impl Default for FakeClock {
+    fn default() -> FakeClock {
+        FakeClock(std::rc::Rc::new(std::cell::Cell::new(Instant::now())))
+    }
+}
+
+impl FakeClock {
+    pub fn advance(&self, by: std::time::Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
@@ -0,0 +1,228 @@
+use super::timer::Timers;
+use super::Timer;
+use crate::internal::WindowEvent;
+use std::time::Duration;
+
+// Sits between the backend and handle_event. Opening a program tends to create, destroy and
+// focus a handful of windows within a few milliseconds, so events are held for a short while
+// and what cancels out is dropped before anything gets laid out:
+//
+// - a window created and destroyed again within the batch never shows up at all
+// - only the last focus change, cursor position and monitor change are kept, as long as nothing
+//   that changes the tree came in between, since where a new window goes depends on focus
+// - a window moving itself repeatedly is reported once
+//
+// Commands are what the user is waiting for, one lets everything held back through at once.
+// The events are handed over when the Coalesce timer fires.
+pub struct Coalescer<W> {
+    delay: Duration,
+    pending: Vec<WindowEvent<W>>,
+}
+
+impl<W: Copy + PartialEq> Coalescer<W> {
+    pub fn new(delay: Duration) -> Coalescer<W> {
+        Coalescer { delay, pending: Vec::new() }
+    }
+
+    pub fn push(&mut self, event: WindowEvent<W>, timers: &mut Timers<Timer>) {
+        // counted from the first event, so a steady stream can't hold everything back forever
+        if !timers.is_scheduled(Timer::Coalesce) {
+            timers.once(Timer::Coalesce, self.delay);
+        }
+        match &event {
+            WindowEvent::Destroyed(window) if self.pending.contains(&WindowEvent::Created(*window)) => {
+                // handles get reused, what came before the window was created is about another one
+                let window = *window;
+                let created = self.pending.iter().rposition(|pending| *pending == WindowEvent::Created(window));
+                let since = self.pending.split_off(created.unwrap_or_default());
+                self.pending.extend(since.into_iter().filter(|pending| pending.window() != Some(window)));
+                return;
+            }
+            WindowEvent::FocusChanged(_) => {
+                self.collapse(|pending| matches!(pending, WindowEvent::FocusChanged(_)))
+            }
+            WindowEvent::MouseMoved(..) => {
+                self.collapse(|pending| matches!(pending, WindowEvent::MouseMoved(..)))
+            }
+            WindowEvent::MonitorsChanged(_) => {
+                self.collapse(|pending| matches!(pending, WindowEvent::MonitorsChanged(_)))
+            }
+            WindowEvent::Relocated(_) if self.pending.contains(&event) => return,
+            _ => (),
+        }
+        if let WindowEvent::Command(_) = event {
+            timers.once(Timer::Coalesce, Duration::ZERO);
+        }
+        self.pending.push(event);
+    }
+
+    // Drops the earlier event of the same kind, unless something that changes the tree is
+    // between them.
+    fn collapse(&mut self, same: impl Fn(&WindowEvent<W>) -> bool) {
+        let start = self.pending.iter().rposition(|pending| !replaceable(pending)).map_or(0, |i| i + 1);
+        if let Some(i) = self.pending[start..].iter().position(same) {
+            self.pending.remove(start + i);
+        }
+    }
+
+    // The events held back, in the order they came in.
+    pub fn take(&mut self) -> Vec<WindowEvent<W>> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// Events that only matter for their latest value, anything else changes the tree.
+fn replaceable<W>(event: &WindowEvent<W>) -> bool {
+    matches!(
+        event,
+        WindowEvent::FocusChanged(_)
+            | WindowEvent::MouseMoved(..)
+            | WindowEvent::MonitorsChanged(_)
+            | WindowEvent::Relocated(_)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::command::Command;
+    use crate::core::clock::FakeClock;
+
+    const DELAY: Duration = Duration::from_millis(30);
+
+    struct Fixture {
+        coalescer: Coalescer<usize>,
+        timers: Timers<Timer>,
+        clock: FakeClock,
+    }
+
+    impl Fixture {
+        fn push(&mut self, event: WindowEvent<usize>) {
+            self.coalescer.push(event, &mut self.timers);
+        }
+
+        // what the loop would handle now
+        fn take_ready(&mut self) -> Vec<WindowEvent<usize>> {
+            if self.timers.take_due().contains(&Timer::Coalesce) {
+                self.coalescer.take()
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    fn coalescer(delay: Duration) -> Fixture {
+        let clock = FakeClock::default();
+        Fixture { coalescer: Coalescer::new(delay), timers: Timers::new(Box::new(clock.clone())), clock }
+    }
+
+    #[test]
+    fn take_ready_should_hold_events_for_the_delay() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::Created(1));
+        coalescer.clock.advance(Duration::from_millis(20));
+        coalescer.push(WindowEvent::Created(2));
+        assert!(coalescer.take_ready().is_empty());
+
+        // counted from the first event, so a steady stream can't hold everything back forever
+        coalescer.clock.advance(Duration::from_millis(10));
+        assert_eq!(vec![WindowEvent::Created(1), WindowEvent::Created(2)], coalescer.take_ready());
+        assert!(coalescer.take_ready().is_empty());
+    }
+
+    #[test]
+    fn push_should_drop_window_created_and_destroyed_within_batch() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::Destroyed(5));
+        coalescer.push(WindowEvent::Created(1));
+        coalescer.push(WindowEvent::FocusChanged(1));
+        coalescer.push(WindowEvent::Created(2));
+        coalescer.push(WindowEvent::Destroyed(1));
+        coalescer.clock.advance(DELAY);
+
+        // 5 was created before the batch, so its destruction still matters
+        assert_eq!(vec![WindowEvent::Destroyed(5), WindowEvent::Created(2)], coalescer.take_ready());
+    }
+
+    #[test]
+    fn push_should_keep_destruction_of_older_window_with_reused_handle() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::Destroyed(1));
+        coalescer.push(WindowEvent::Created(1));
+        coalescer.push(WindowEvent::FocusChanged(1));
+        coalescer.push(WindowEvent::Destroyed(1));
+        coalescer.clock.advance(DELAY);
+
+        assert_eq!(vec![WindowEvent::Destroyed(1)], coalescer.take_ready());
+    }
+
+    #[test]
+    fn push_should_keep_only_last_focus_cursor_and_monitors() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::FocusChanged(1));
+        coalescer.push(WindowEvent::MouseMoved(0, 0));
+        coalescer.push(WindowEvent::MonitorsChanged(Vec::new()));
+        coalescer.push(WindowEvent::Relocated(3));
+        coalescer.push(WindowEvent::FocusChanged(2));
+        coalescer.push(WindowEvent::MouseMoved(10, 10));
+        coalescer.push(WindowEvent::Relocated(3));
+        coalescer.push(WindowEvent::MonitorsChanged(Vec::new()));
+        coalescer.clock.advance(DELAY);
+
+        assert_eq!(
+            vec![
+                WindowEvent::Relocated(3),
+                WindowEvent::FocusChanged(2),
+                WindowEvent::MouseMoved(10, 10),
+                WindowEvent::MonitorsChanged(Vec::new()),
+            ],
+            coalescer.take_ready()
+        );
+    }
+
+    #[test]
+    fn push_should_keep_focus_changes_around_new_windows() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::FocusChanged(1));
+        coalescer.push(WindowEvent::FocusChanged(2));
+        coalescer.push(WindowEvent::Created(3));
+        coalescer.push(WindowEvent::FocusChanged(1));
+        coalescer.push(WindowEvent::Relocated(3));
+        coalescer.push(WindowEvent::FocusChanged(3));
+        coalescer.clock.advance(DELAY);
+
+        assert_eq!(
+            vec![
+                WindowEvent::FocusChanged(2),
+                WindowEvent::Created(3),
+                WindowEvent::Relocated(3),
+                WindowEvent::FocusChanged(3),
+            ],
+            coalescer.take_ready()
+        );
+    }
+
+    #[test]
+    fn command_should_let_everything_through_at_once() {
+        let mut coalescer = coalescer(DELAY);
+
+        coalescer.push(WindowEvent::Created(1));
+        coalescer.push(WindowEvent::Command(Command::Balance));
+
+        assert_eq!(vec![WindowEvent::Created(1), WindowEvent::Command(Command::Balance)], coalescer.take_ready());
+    }
+
+    #[test]
+    fn take_ready_should_pass_events_straight_through_without_delay() {
+        let mut coalescer = coalescer(Duration::ZERO);
+
+        coalescer.push(WindowEvent::Created(1));
+
+        assert_eq!(vec![WindowEvent::Created(1)], coalescer.take_ready());
+    }
+}
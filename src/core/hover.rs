@@ -0,0 +1,87 @@
+use super::timer::Timers;
+use super::Timer;
+use std::time::Duration;
+
+// Focus follows the mouse: the window under the cursor gets focus once the cursor has rested on
+// it for the delay, so windows crossed on the way somewhere else keep their hands off focus.
+pub struct Hover<W> {
+    // None while focus doesn't follow the mouse
+    pub delay: Option<Duration>,
+    pending: Option<W>,
+}
+
+impl<W: Copy + PartialEq> Hover<W> {
+    pub fn new(delay: Option<Duration>) -> Hover<W> {
+        Hover { delay, pending: None }
+    }
+
+    // window is the one under the cursor now, if it's one that should get focus. Moving onto
+    // another window starts the wait over.
+    pub fn moved(&mut self, window: Option<W>, timers: &mut Timers<Timer>) {
+        let delay = match self.delay {
+            Some(delay) => delay,
+            None => return,
+        };
+        match window {
+            Some(window) if self.pending == Some(window) => (),
+            Some(window) => {
+                self.pending = Some(window);
+                timers.once(Timer::Hover, delay);
+            }
+            None => {
+                self.pending = None;
+                timers.cancel(Timer::Hover);
+            }
+        }
+    }
+
+    // The window to focus, once the hover timer fired.
+    pub fn take(&mut self) -> Option<W> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+
+    fn timers() -> (Timers<Timer>, FakeClock) {
+        let clock = FakeClock::default();
+        (Timers::new(Box::new(clock.clone())), clock)
+    }
+
+    #[test]
+    fn moved_should_wait_for_cursor_to_rest_on_window() {
+        let (mut timers, clock) = timers();
+        let mut hover = Hover::new(Some(Duration::from_millis(200)));
+
+        hover.moved(Some(1), &mut timers);
+        clock.advance(Duration::from_millis(100));
+        hover.moved(Some(2), &mut timers);
+        clock.advance(Duration::from_millis(100));
+        assert!(timers.take_due().is_empty());
+
+        hover.moved(Some(2), &mut timers);
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(vec![Timer::Hover], timers.take_due());
+        assert_eq!(Some(2), hover.take());
+        assert_eq!(None, hover.take());
+
+        hover.moved(Some(1), &mut timers);
+        hover.moved(None, &mut timers);
+        clock.advance(Duration::from_millis(500));
+        assert!(timers.take_due().is_empty());
+    }
+
+    #[test]
+    fn moved_should_do_nothing_without_delay() {
+        let (mut timers, _clock) = timers();
+        let mut hover = Hover::new(None);
+
+        hover.moved(Some(1), &mut timers);
+
+        assert!(!timers.is_scheduled(Timer::Hover));
+        assert_eq!(None, hover.take());
+    }
+}
@@ -1,110 +1,1911 @@
-extern crate winapi;
+pub mod clock;
+pub mod coalesce;
+pub mod hover;
+pub mod reconcile;
+pub mod record;
+pub mod replay;
+pub mod session;
+pub mod timer;
 
-use crate::internal;
+use crate::command::Command;
+use crate::config::Config;
+use crate::internal::{self, Backend, Handle};
+use crate::layout::{self, template, Layout};
+use crate::monitor;
+use crate::scratchpad::{self, Scratchpad};
 use crate::tile;
-use winapi::{shared::{windef}};
+use crate::workspace::{Preselection, Workspace, Zoom, ZoomArea};
+use clock::{Clock, SystemClock};
+use coalesce::Coalescer;
+use hover::Hover;
+use reconcile::Reconcile;
+use record::Recorder;
+use session::Session;
+use timer::Timers;
 
-pub fn run() -> Result<i32, std::io::Error> {
-    let init_windows = internal::get_initial_windows();
-    let win_dimensions = internal::get_window_dimensions();
+// how far from a separator a dragged window edge can be and still count as being on it
+const EDGE_TOLERANCE: i32 = 8;
 
-    let root = tile_existing_windows(init_windows, win_dimensions);
-    redraw_nodes(&root);
+// What the core loop waits for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timer {
+    // the cursor rested on a window long enough to focus it
+    Hover,
+    // placed windows had time to settle and can be checked
+    Reconcile,
+    // changes stopped coming in for long enough to save the session
+    SaveSession,
+    // window events were held back long enough to be handled
+    Coalesce,
+}
+
+pub struct State<W> {
+    pub workspaces: Vec<Workspace<W>>,
+    pub current_workspace: usize,
+    pub orientation: tile::Orientation,
+    pub insertion: tile::Insertion,
+    pub scratchpad: Scratchpad<W>,
+    // windows tiles10 hid itself, whose hidden events aren't the user minimizing them
+    pub hidden: Vec<W>,
+    pub hover: Hover<W>,
+    pub mouse_follows_focus: bool,
+    pub reconcile: Reconcile<W>,
+    pub timers: Timers<Timer>,
+    pub monitors: Vec<monitor::Monitor>,
+    // edges of monitors kept out of the layout, by monitor id
+    pub reservations: Vec<monitor::Reservation>,
+    // what went wrong without stopping anything, for whoever runs the loop to report
+    pub errors: Vec<String>,
+}
+
+impl<W: Copy + PartialEq> State<W> {
+    pub fn new(workspaces: Vec<Workspace<W>>, clock: Box<dyn Clock>) -> State<W> {
+        State {
+            workspaces,
+            current_workspace: 0,
+            orientation: tile::Orientation::Horizontal,
+            insertion: tile::Insertion::SplitFocused,
+            scratchpad: Scratchpad::new(Vec::new()),
+            hidden: Vec::new(),
+            hover: Hover::new(None),
+            mouse_follows_focus: false,
+            reconcile: Reconcile::new(false),
+            timers: Timers::new(clock),
+            monitors: Vec::new(),
+            reservations: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn workspace(&self) -> &Workspace<W> {
+        &self.workspaces[self.current_workspace]
+    }
+
+    pub fn workspace_mut(&mut self) -> &mut Workspace<W> {
+        &mut self.workspaces[self.current_workspace]
+    }
+}
+
+pub fn run<B: Backend>(
+    mut backend: B,
+    config: &Config,
+    mut recorder: Option<Recorder>,
+    session: Option<Session>,
+    mut report: impl FnMut(String),
+) -> Result<i32, std::io::Error> {
+    let saved = match &session {
+        Some(session) => session.load().unwrap_or_else(|e| {
+            report(e);
+            None
+        }),
+        None => None,
+    };
+    if let Some(recorder) = &mut recorder {
+        recorder.record_start(&backend, config, saved.as_ref())?;
+    }
 
-    hook_and_loop(root);
+    let mut state = start(&mut backend, saved.as_ref(), config, Box::new(SystemClock));
+    hook_and_loop(&mut backend, config, &mut state, recorder, session, &mut report)?;
 
     Ok(0)
 }
 
-fn hook_and_loop(mut root: tile::Node<windef::HWND>) {
-    internal::create_hooks();
-    let mut current_focus = internal::get_active_window();
-    let mut orientation = tile::Orientation::Horizontal;
+pub fn start<B: Backend>(
+    backend: &mut B,
+    saved: Option<&Layout>,
+    config: &Config,
+    clock: Box<dyn Clock>,
+) -> State<B::Window> {
+    let init_windows = backend.get_initial_windows();
+    let monitors = backend.get_monitors();
+    let reservations = monitor::resolve(&config.reservations, &monitors);
+    let primary = monitors.first().map_or("", |monitor| monitor.id.as_str());
+    let reserved = monitor::reserved(&reservations, primary);
+    let win_dimensions = monitor::work_area(&backend.get_window_dimensions(), &reserved);
+
+    let mut state = match saved {
+        Some(layout) => restore_layout(backend, layout, init_windows, win_dimensions, clock),
+        None => {
+            let mut workspace = Workspace::new("1", win_dimensions);
+            tile_existing_windows(&mut workspace.root, init_windows);
+            if let Some(active) = backend.get_active_window() {
+                change_focused_window(&mut workspace, active);
+            }
+
+            State::new(vec![workspace], clock)
+        }
+    };
+    // everything was shown when tiles10 last exited, what doesn't belong on screen goes away again
+    for (i, workspace) in state.workspaces.iter().enumerate() {
+        if i == state.current_workspace {
+            continue;
+        }
+        for window in workspace.windows() {
+            hide_window(backend, &mut state.hidden, window);
+        }
+    }
+    for scratch in state.scratchpad.windows.iter().filter(|scratch| !scratch.visible) {
+        hide_window(backend, &mut state.hidden, scratch.window);
+    }
+    state.insertion = config.insertion;
+    state.scratchpad.named = config.scratchpads.clone();
+    state.hover = Hover::new(config.focus_follows_mouse);
+    state.mouse_follows_focus = config.mouse_follows_focus;
+    state.reconcile = Reconcile::new(config.enforce_layout);
+    state.monitors = monitors;
+    state.reservations = reservations;
+    // everything starts out on the primary monitor
+    for workspace in &mut state.workspaces {
+        workspace.monitor = state.monitors.first().cloned();
+        workspace.home = workspace.monitor.as_ref().map(|monitor| monitor.id.clone());
+        workspace.gaps = config.gaps;
+        workspace.overflow = config.overflow;
+    }
+    redraw(backend, state.workspace_mut());
+    check_placement(&mut state);
+
+    state
+}
+
+fn restore_layout<B: Backend>(
+    backend: &B,
+    layout: &Layout,
+    windows: Vec<B::Window>,
+    dim: tile::Dimensions,
+    clock: Box<dyn Clock>,
+) -> State<B::Window> {
+    let mut live: Vec<_> = windows
+        .into_iter()
+        .map(|window| (window, backend.get_window_info(window)))
+        .collect();
+    // windows hidden on other workspaces or in the scratchpad aren't enumerated, they're found by
+    // the handle the session kept as long as it still belongs to the same kind of window
+    for (id, saved) in layout::adopt::saved_windows(layout) {
+        let window = B::Window::from_id(id);
+        if live.iter().any(|(live, _)| *live == window) {
+            continue;
+        }
+        let info = backend.get_window_info(window);
+        if !info.class.is_empty() && info.class == saved.class && info.process == saved.process {
+            live.push((window, info));
+        }
+    }
+    let (mut state, unmatched) = layout::adopt::adopt(layout, &live, &dim, clock);
+
+    let workspace = state.workspace_mut();
+    let unmatched = unmatched
+        .into_iter()
+        .filter(|window| !workspace.claim_placeholder(*window, &backend.get_window_info(*window)))
+        .collect();
+    tile_existing_windows(&mut workspace.root, unmatched);
+    if let Some(active) = backend.get_active_window().filter(|_| workspace.focus.is_none()) {
+        change_focused_window(workspace, active);
+    }
+
+    state
+}
+
+fn hook_and_loop<B: Backend>(
+    backend: &mut B,
+    config: &Config,
+    state: &mut State<B::Window>,
+    mut recorder: Option<Recorder>,
+    mut session: Option<Session>,
+    mut report: impl FnMut(String),
+) -> Result<(), std::io::Error> {
+    state.errors.extend(backend.create_hooks(&config.bindings));
+    if state.hover.delay.is_some() {
+        backend.watch_mouse();
+    }
+    let mut coalescer = Coalescer::new(config.coalesce);
+    if let Some(session) = &mut session {
+        // only changes from here on need saving
+        session.changed(state);
+    }
     loop {
-        if let Some(event) = internal::send_message() {
-            match event {
-                internal::WindowEvent::Created(window) => {
-                    tile_new_window(&mut root, window, current_focus, orientation);
+        while let Some(event) = backend.next_event() {
+            coalescer.push(event, &mut state.timers);
+        }
+
+        for timer in state.timers.take_due() {
+            if timer != Timer::Coalesce {
+                run_timer(backend, state, session.as_ref(), timer);
+                continue;
+            }
+            // what's recorded is what the layout saw, so replaying gives the same result
+            for event in coalescer.take() {
+                if let Some(recorder) = &mut recorder {
+                    recorder.record_event(backend, &event)?;
+                }
+                if event == internal::WindowEvent::Command(Command::Quit) {
+                    if let Some(Err(e)) = session.as_ref().map(|session| session.save(state, backend)) {
+                        report(e);
+                    }
+                    release(backend, state);
+                    return Ok(());
+                }
+                handle_event(backend, state, event);
+                if session.as_mut().is_some_and(|session| session.changed(state)) {
+                    state.timers.once(Timer::SaveSession, session::SAVE_DELAY);
+                }
+            }
+        }
+
+        for error in state.errors.drain(..) {
+            report(error);
+        }
+        // nothing to do until the next event or timer
+        backend.wait(state.timers.next_due());
+    }
+}
+
+// Shows every window tiles10 manages, so none stay hidden once it's gone.
+fn release<B: Backend>(backend: &mut B, state: &State<B::Window>) {
+    for workspace in &state.workspaces {
+        for window in workspace.windows() {
+            backend.show_window(window);
+        }
+    }
+    for scratch in &state.scratchpad.windows {
+        backend.show_window(scratch.window);
+    }
+}
+
+// Does whatever has waited long enough.
+pub fn run_timers<B: Backend>(backend: &mut B, state: &mut State<B::Window>, session: Option<&Session>) {
+    for timer in state.timers.take_due() {
+        run_timer(backend, state, session, timer);
+    }
+}
+
+fn run_timer<B: Backend>(backend: &mut B, state: &mut State<B::Window>, session: Option<&Session>, timer: Timer) {
+    match timer {
+        Timer::Hover => focus_hovered(backend, state),
+        Timer::Reconcile => reconcile(backend, state),
+        Timer::SaveSession => {
+            if let Some(Err(e)) = session.map(|session| session.save(state, backend)) {
+                state.errors.push(e);
+            }
+        }
+        // only the loop holds events back, and it hands them over itself
+        Timer::Coalesce => (),
+    }
+}
+
+pub fn handle_event<B: Backend>(
+    backend: &mut B,
+    state: &mut State<B::Window>,
+    event: internal::WindowEvent<B::Window>,
+) {
+    let before = (state.current_workspace, state.workspace().focus);
+    apply_event(backend, state, event);
+    check_placement(state);
+    // whatever moved focus, a command, a window opening or closing or the system
+    if state.mouse_follows_focus && (state.current_workspace, state.workspace().focus) != before {
+        move_cursor_to_focus(backend, state.workspace());
+    }
+}
+
+// A new placement gets checked a little later.
+fn check_placement<W: Copy + PartialEq>(state: &mut State<W>) {
+    let workspace = &state.workspaces[state.current_workspace];
+    state.reconcile.placed(&workspace.placed, &mut state.timers);
+}
+
+fn apply_event<B: Backend>(
+    backend: &mut B,
+    state: &mut State<B::Window>,
+    event: internal::WindowEvent<B::Window>,
+) {
+    let orientation = state.orientation;
+    let insertion = state.insertion;
+    match event {
+        internal::WindowEvent::Created(window) => {
+            // a window launched for a named scratchpad floats instead of being tiled
+            if state.scratchpad.claim(window, &backend.get_window_info(window)) {
+                show_floating(backend, state.workspace(), window);
+                return;
+            }
+
+            // a handle can be used again by a new window, which asks for its own size
+            for workspace in &mut state.workspaces {
+                workspace.forget_hints(window);
+            }
+            // new windows end the zoom, so they aren't opened behind it
+            let workspace = state.workspace_mut();
+            workspace.zoom = None;
+            tile_new_window(backend, workspace, window, orientation, insertion);
+        }
+        internal::WindowEvent::Destroyed(window) => {
+            if state.scratchpad.remove(window) {
+                return;
+            }
+            state.reconcile.forget(window);
+            state.hidden.retain(|hidden| *hidden != window);
+            for workspace in &mut state.workspaces {
+                workspace.take_minimized(window);
+                workspace.forget_hints(window);
+            }
+            match state.workspaces.iter().position(|workspace| workspace.contains(window)) {
+                Some(i) if i == state.current_workspace => untile_window(backend, state.workspace_mut(), window),
+                // windows on hidden workspaces are laid out again once the workspace is shown
+                Some(i) => state.workspaces[i].remove_window(window),
+                None => (),
+            }
+        }
+        internal::WindowEvent::FocusChanged(window) => focus_changed(backend, state.workspace_mut(), window),
+        internal::WindowEvent::Hidden(window) => {
+            // hiding a window for another workspace or the scratchpad reports back here too,
+            // possibly after switching back already showed it again
+            if let Some(i) = state.hidden.iter().position(|hidden| *hidden == window) {
+                state.hidden.remove(i);
+                return;
+            }
+            let workspace = state.workspace_mut();
+            if let Some(place) = workspace.place(window) {
+                untile_window(backend, workspace, window);
+                workspace.minimized.push((window, place));
+            }
+        }
+        internal::WindowEvent::Shown(window) => {
+            let found = state
+                .workspaces
+                .iter_mut()
+                .enumerate()
+                .find_map(|(i, workspace)| workspace.take_minimized(window).map(|place| (i, place)));
+            match found {
+                Some((i, place)) if i == state.current_workspace => {
+                    let workspace = state.workspace_mut();
+                    workspace.put_back(window, place);
+                    rearrange(backend, workspace);
                 }
-                internal::WindowEvent::Destroyed(window) => {
-                    untile_window(&mut root, window);
+                // restored while another workspace is showing, so it joins that one
+                Some(_) => tile_new_window(backend, state.workspace_mut(), window, orientation, insertion),
+                None => (),
+            }
+        }
+        internal::WindowEvent::Moved(window, dim, point) => {
+            window_moved(backend, state.workspace_mut(), window, dim, point)
+        }
+        internal::WindowEvent::Relocated(window) => {
+            // it may have moved to a monitor with another scale, or changed what it needs
+            let workspace = state.workspace_mut();
+            let placed = workspace.placed.iter().find(|(placed, _)| *placed == window);
+            if placed.is_some_and(|(_, dim)| backend.get_window_rect(window).as_ref() != Some(dim)) {
+                workspace.forget_hints(window);
+            }
+            state.reconcile.relocated(window, &mut state.timers);
+        }
+        internal::WindowEvent::MonitorsChanged(monitors) => monitors_changed(backend, state, monitors),
+        internal::WindowEvent::MouseMoved(x, y) => {
+            let workspace = state.workspace();
+            // floating windows are over the tiled ones, and a zoom is over everything
+            let covered = workspace.zoom.is_some()
+                || workspace.floating.iter().any(|node| tile::window_at(node, x, y).is_some());
+            let hovered = tile::window_at(&workspace.root, x, y)
+                .filter(|window| !covered && workspace.focus != Some(*window));
+            state.hover.moved(hovered, &mut state.timers);
+        }
+        internal::WindowEvent::Command(command) => run_command(backend, state, command),
+    }
+}
+
+// Puts every workspace on the monitor it belongs on, or on the primary while that one isn't
+// connected, and lays it out again there. Trees keep their shape and floating windows keep
+// their place relative to the workspace.
+fn monitors_changed<B: Backend>(backend: &mut B, state: &mut State<B::Window>, monitors: Vec<monitor::Monitor>) {
+    // there are none for a moment while displays are being rearranged
+    if monitors.is_empty() {
+        return;
+    }
+
+    let homes: Vec<_> = state.workspaces.iter().map(|workspace| workspace.home.clone()).collect();
+    let placed = monitor::place(&homes, &monitors);
+    for (workspace, i) in state.workspaces.iter_mut().zip(placed) {
+        let reserved = monitor::reserved(&state.reservations, &monitors[i].id);
+        let area = monitor::work_area(&monitors[i].work_area, &reserved);
+        let old = std::mem::replace(&mut workspace.root.dim, area.clone());
+        tile::resize_children(&mut workspace.root);
+        for node in &mut workspace.floating {
+            node.dim = monitor::rescale(&node.dim, &old, &area);
+            tile::resize_children(node);
+        }
+        workspace.monitor = Some(monitors[i].clone());
+        // limits are in physical pixels, a monitor with another scale changes them
+        workspace.hints.clear();
+        workspace.retile_overflowed();
+    }
+    state.monitors = monitors;
+    redraw(backend, state.workspace_mut());
+}
+
+// Focuses the window the mouse has been resting on long enough.
+fn focus_hovered<B: Backend>(backend: &mut B, state: &mut State<B::Window>) {
+    if let Some(window) = state.hover.take() {
+        let workspace = state.workspace_mut();
+        if workspace.contains(window) {
+            focus_window(backend, workspace, window);
+        }
+    }
+}
+
+// Puts windows that didn't end up where the last redraw put them back there, until they've had
+// all their attempts.
+fn reconcile<B: Backend>(backend: &mut B, state: &mut State<B::Window>) {
+    let workspace = &state.workspaces[state.current_workspace];
+    let mut retried = false;
+    for (window, dim) in &workspace.placed {
+        if state.reconcile.refusing.contains(window) || backend.get_window_rect(*window).as_ref() == Some(dim) {
+            continue;
+        }
+        // once it has had all its attempts the window is left alone, it's refusing from then on
+        if state.reconcile.retry(*window) {
+            backend.set_window_pos(*window, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+            retried = true;
+        }
+    }
+    if retried {
+        state.reconcile.check_later(&mut state.timers);
+    }
+}
+
+fn move_cursor_to_focus<B: Backend>(backend: &mut B, workspace: &Workspace<B::Window>) {
+    let dim = workspace.focus.and_then(|focus| {
+        std::iter::once(&workspace.root)
+            .chain(&workspace.floating)
+            .find_map(|node| tile::find_path(node, &focus).and_then(|path| tile::node_at(node, &path)))
+            .map(|node| &node.dim)
+    });
+    let dim = match dim {
+        Some(dim) => dim,
+        None => return,
+    };
+    // focus that came from the mouse, by clicking or resting on a window, leaves the cursor be,
+    // moving it would only start the hover over
+    let inside = |(x, y): (i32, i32)| x >= dim.x.0 && x < dim.x.0 + dim.x.1 && y >= dim.y.0 && y < dim.y.0 + dim.y.1;
+    if !backend.get_cursor_pos().is_some_and(inside) {
+        backend.move_cursor(dim.x.0 + dim.x.1 / 2, dim.y.0 + dim.y.1 / 2);
+    }
+}
+
+pub fn run_command<B: Backend>(backend: &mut B, state: &mut State<B::Window>, command: Command) {
+    match command {
+        Command::ToggleOrientation => {
+            if state.orientation == tile::Orientation::Horizontal {
+                state.orientation = tile::Orientation::Vertical
+            } else {
+                state.orientation = tile::Orientation::Horizontal;
+            }
+        }
+        Command::LoadTemplate(path) => match template::load(&path) {
+            Ok(template) => {
+                let (orientation, insertion) = (state.orientation, state.insertion);
+                let workspace = state.workspace_mut();
+                let node = template.root.to_node(workspace);
+                insert(workspace, orientation, insertion, node);
+                rearrange(backend, workspace);
+            }
+            Err(e) => state.errors.push(format!("could not load template: {}", e)),
+        },
+        Command::ClearPlaceholders => {
+            let workspace = state.workspace_mut();
+            workspace.clear_placeholders();
+            rearrange(backend, workspace);
+        }
+        Command::SetInsertion(insertion) => state.insertion = insertion,
+        Command::Preselect(direction, ratio) => {
+            let workspace = state.workspace_mut();
+            workspace.preselection = Some(Preselection { window: workspace.focus, direction, ratio });
+        }
+        Command::CancelPreselection => state.workspace_mut().preselection = None,
+        Command::ToggleSplit => change_focused_container(backend, state.workspace_mut(), tile::toggle_orientation),
+        Command::Rotate(rotation) => {
+            change_focused_container(backend, state.workspace_mut(), |node| tile::rotate(node, rotation))
+        }
+        Command::Mirror(orientation) => {
+            change_focused_container(backend, state.workspace_mut(), |node| tile::mirror(node, orientation))
+        }
+        Command::Balance => {
+            let workspace = state.workspace_mut();
+            tile::balance(&mut workspace.root);
+            redraw(backend, workspace);
+        }
+        Command::Equalize => {
+            let workspace = state.workspace_mut();
+            let container = workspace.focus.and_then(|focus| tile::find_container(&mut workspace.root, focus));
+            if let Some(container) = container {
+                tile::equalize(container);
+                redraw(backend, workspace);
+            }
+        }
+        Command::ToggleFullscreen => toggle_zoom(backend, state.workspace_mut(), ZoomArea::Monitor),
+        Command::ToggleZoom => toggle_zoom(backend, state.workspace_mut(), ZoomArea::Workspace),
+        Command::MoveToScratchpad => {
+            let workspace = state.workspace_mut();
+            let focused = workspace.focus.filter(|focus| workspace.contains(*focus));
+            if let Some(window) = focused {
+                untile_window(backend, workspace, window);
+                hide_window(backend, &mut state.hidden, window);
+                state.scratchpad.add(window, None, false);
+            }
+        }
+        Command::ToggleScratchpad(name) => match state.scratchpad.toggle(name.as_deref()) {
+            scratchpad::Action::Show(window) => show_floating(backend, state.workspace(), window),
+            scratchpad::Action::Hide(window) => hide_window(backend, &mut state.hidden, window),
+            scratchpad::Action::Launch(command_line) => {
+                if let Err(e) = backend.launch(&command_line) {
+                    state.scratchpad.launch_failed(name.as_deref().unwrap_or_default());
+                    state.errors.push(e);
                 }
-                internal::WindowEvent::FocusChanged(window) => {
-                    change_focused_window(&mut root, window, &mut current_focus);
+            }
+            scratchpad::Action::Nothing => (),
+        },
+        Command::FocusLast => {
+            let workspace = state.workspace_mut();
+            if let Some(last) = workspace.history.get(1).copied() {
+                focus_window(backend, workspace, last);
+            }
+        }
+        Command::FocusNext | Command::FocusPrev => {
+            let workspace = state.workspace_mut();
+            let windows = tile::windows(&workspace.root);
+            let current = workspace.focus.and_then(|focus| windows.iter().position(|w| *w == focus));
+            let next = match (current, command) {
+                (Some(i), Command::FocusNext) => windows.get((i + 1) % windows.len()),
+                (Some(i), _) => windows.get((i + windows.len() - 1) % windows.len()),
+                (None, _) => windows.first(),
+            };
+            if let Some(next) = next.copied() {
+                focus_window(backend, workspace, next);
+            }
+        }
+        Command::FocusParent => {
+            let workspace = state.workspace_mut();
+            if let Some(path) = workspace.selected_path() {
+                if let Some((_, parent)) = path.split_last() {
+                    workspace.selection = Some(parent.to_vec());
                 }
-                internal::WindowEvent::OrientationChanged => {
-                    if orientation == tile::Orientation::Horizontal {
-                        println!("swapped oritentation to vertical");
-                        orientation = tile::Orientation::Vertical
-                    } else {
-                        println!("swapped oritentation to horizontal");
-                        orientation = tile::Orientation::Horizontal;
+            }
+        }
+        Command::FocusChild => {
+            let workspace = state.workspace_mut();
+            if let Some(selection) = workspace.selection.take() {
+                // back down towards the focused window
+                let focus_path = workspace.focus.and_then(|focus| tile::find_path(&workspace.root, &focus));
+                workspace.selection = match focus_path {
+                    Some(path) if path.starts_with(&selection) && path.len() > selection.len() + 1 => {
+                        Some(path[..selection.len() + 1].to_vec())
                     }
+                    _ => None,
+                };
+            }
+        }
+        Command::Move(direction) => {
+            let workspace = state.workspace_mut();
+            if let Some(path) = workspace.selected_path() {
+                let moved = tile::move_node(&mut workspace.root, &path, direction);
+                if workspace.selection.is_some() {
+                    workspace.selection = moved;
                 }
+                redraw(backend, workspace);
+            }
+        }
+        Command::Resize(change) => {
+            let workspace = state.workspace_mut();
+            if let Some(path) = workspace.selected_path() {
+                tile::resize_node(&mut workspace.root, &path, change);
+                redraw(backend, workspace);
             }
         }
+        Command::ToggleFloating => {
+            let (orientation, insertion) = (state.orientation, state.insertion);
+            toggle_floating(backend, state.workspace_mut(), orientation, insertion);
+        }
+        Command::SwitchWorkspace(name) => switch_workspace(backend, state, &name),
+        Command::SendToWorkspace(name) => send_to_workspace(backend, state, &name),
+        Command::ToggleAutoBalance => {
+            let workspace = state.workspace_mut();
+            workspace.auto_balance = !workspace.auto_balance;
+            rearrange(backend, workspace);
+        }
+        // the loop stops before handing it over
+        Command::Quit => (),
     }
 }
 
-fn tile_new_window(
-    mut root: &mut tile::Node<windef::HWND>,
-    window: windef::HWND,
-    prev_window: windef::HWND,
-    orientation: tile::Orientation
+fn tile_new_window<B: Backend>(
+    backend: &mut B,
+    workspace: &mut Workspace<B::Window>,
+    window: B::Window,
+    orientation: tile::Orientation,
+    insertion: tile::Insertion,
 ) {
-    let focused_node = tile::find_node(&mut root, prev_window);
-    if let Some(last_focused) = focused_node {
-        tile::tile(last_focused, orientation, window);
-    } else {
-        tile::tile(&mut root, orientation, window);
+    // a template slot waiting for this window wins over splitting the focused one
+    if !workspace.claim_placeholder(window, &backend.get_window_info(window)) {
+        let node = tile::Node {
+            node_type: tile::NodeType::Window(window),
+            dim: workspace.root.dim.clone(),
+        };
+        insert(workspace, orientation, insertion, node);
     }
-    redraw_nodes(&root);
+    rearrange(backend, workspace);
 }
 
-fn untile_window(mut root: &mut tile::Node<windef::HWND>, window: windef::HWND) {
-    tile::untile(&mut root, &window);
-    redraw_nodes(&root);
+// Adds the node where the preselection says, or where the insertion policy puts it when nothing
+// is preselected.
+fn insert<W: internal::Handle>(
+    workspace: &mut Workspace<W>,
+    orientation: tile::Orientation,
+    insertion: tile::Insertion,
+    node: tile::Node<W>,
+) {
+    if let Some(presel) = workspace.preselection.take() {
+        let target = match presel.window {
+            Some(window) => tile::find_node(&mut workspace.root, window),
+            None => Some(&mut workspace.root),
+        };
+        if let Some(target) = target {
+            tile::split(target, presel.direction, presel.ratio, node);
+            return;
+        }
+    }
+
+    tile::insert(&mut workspace.root, workspace.focus, insertion, orientation, node);
 }
 
-fn change_focused_window<'a>(mut root: &mut tile::Node<windef::HWND>, window: windef::HWND,
-    current: &'a mut windef::HWND) {
-    if let Some(_) = tile::find_node(&mut root, window) {
-        *current = window;
+// Applies change to the separator holding the focused window.
+fn change_focused_container<B: Backend, F: FnOnce(&mut tile::Node<B::Window>)>(
+    backend: &mut B,
+    workspace: &mut Workspace<B::Window>,
+    change: F,
+) {
+    let container = workspace.focus.and_then(|focus| tile::find_parent(&mut workspace.root, focus));
+    if let Some(container) = container {
+        change(container);
+        redraw(backend, workspace);
     }
 }
 
-fn tile_existing_windows(
-    mut windows: Vec<windef::HWND>,
-    dim: tile::Dimensions,
-) -> tile::Node<windef::HWND> {
-    let mut root: tile::Node<windef::HWND> = tile::Node {
-        node_type: tile::NodeType::Empty,
-        dim: dim,
+// Floats the selection, or puts the floating subtree holding the focused window back in the
+// tree.
+fn toggle_floating<B: Backend>(
+    backend: &mut B,
+    workspace: &mut Workspace<B::Window>,
+    orientation: tile::Orientation,
+    insertion: tile::Insertion,
+) {
+    let floating = workspace.focus.and_then(|focus| workspace.floating_index(focus));
+    match floating {
+        Some(i) => {
+            let node = workspace.floating.remove(i);
+            // tiled on purpose, it's up to the redraw to float it again if it doesn't fit
+            let windows = tile::windows(&node);
+            workspace.overflowed.retain(|(window, _)| !windows.contains(window));
+            insert(workspace, orientation, insertion, node);
+        }
+        None => {
+            let node = workspace
+                .selected_path()
+                .and_then(|path| tile::take(&mut workspace.root, &path));
+            if let Some(mut node) = node {
+                node.dim = scratchpad::float_dimensions(&workspace.root.dim);
+                tile::resize_children(&mut node);
+                workspace.floating.push(node);
+            }
+        }
+    }
+    rearrange(backend, workspace);
+}
+
+// Index of the workspace with the given name, created if there isn't one yet.
+fn workspace_index<W>(state: &mut State<W>, name: &str) -> usize
+where
+    W: Copy + PartialEq,
+{
+    match state.workspaces.iter().position(|workspace| workspace.name == name) {
+        Some(i) => i,
+        None => {
+            let current = state.workspace();
+            let mut workspace = Workspace::new(name, current.root.dim.clone());
+            workspace.monitor = current.monitor.clone();
+            workspace.home = current.home.clone();
+            workspace.gaps = current.gaps;
+            workspace.overflow = current.overflow;
+            state.workspaces.push(workspace);
+            state.workspaces.len() - 1
+        }
+    }
+}
+
+fn switch_workspace<B: Backend>(backend: &mut B, state: &mut State<B::Window>, name: &str) {
+    let index = workspace_index(state, name);
+    if index == state.current_workspace {
+        return;
+    }
+
+    for window in state.workspace().windows() {
+        hide_window(backend, &mut state.hidden, window);
+    }
+    // everything has to be shown again when coming back
+    state.workspace_mut().placed.clear();
+    state.current_workspace = index;
+    let workspace = state.workspace_mut();
+    rearrange(backend, workspace);
+    if let Some(focus) = workspace.focus {
+        backend.focus_window(focus);
+    }
+}
+
+// Hides a window and remembers that it was tiles10 doing it.
+fn hide_window<B: Backend>(backend: &mut B, hidden: &mut Vec<B::Window>, window: B::Window) {
+    backend.hide_window(window);
+    // hiding a window that's already hidden doesn't report anything
+    if !hidden.contains(&window) {
+        hidden.push(window);
+    }
+}
+
+// Moves the selection to the named workspace, where it's added like a new window would be.
+fn send_to_workspace<B: Backend>(backend: &mut B, state: &mut State<B::Window>, name: &str) {
+    let index = workspace_index(state, name);
+    if index == state.current_workspace {
+        return;
+    }
+
+    let (orientation, insertion) = (state.orientation, state.insertion);
+    let workspace = &mut state.workspaces[state.current_workspace];
+    let node = workspace
+        .selected_path()
+        .and_then(|path| tile::take(&mut workspace.root, &path));
+    let mut node = match node {
+        Some(node) => node,
+        None => return,
+    };
+
+    let slots = tile::placeholders(&node)
+        .into_iter()
+        .filter_map(|slot| workspace.slots.remove(&slot).map(|criteria| (slot, criteria)))
+        .collect();
+    let moved = tile::windows(&node);
+    let had_focus = workspace.focus.is_some_and(|focus| moved.contains(&focus));
+    for window in &moved {
+        workspace.remove_window(*window);
+        hide_window(backend, &mut state.hidden, *window);
+    }
+    rearrange(backend, workspace);
+    if had_focus {
+        if let Some(next) = workspace.focus {
+            focus_window(backend, workspace, next);
+        }
+    }
+
+    let target = &mut state.workspaces[index];
+    target.adopt_slots(&mut node, slots);
+    if let Some(first) = moved.first().filter(|_| target.focus.is_none()) {
+        target.remember_focus(*first);
+    }
+    insert(target, orientation, insertion, node);
+}
+
+// Zooms the focused window into area, or ends the zoom if it already fills that area.
+fn toggle_zoom<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>, area: ZoomArea) {
+    let focused = workspace.focus.filter(|focus| tile::find_node(&mut workspace.root, *focus).is_some());
+    workspace.zoom = match (workspace.zoom, focused) {
+        (Some(zoom), _) if zoom.area == area => None,
+        (_, Some(window)) => Some(Zoom { window, area }),
+        (zoom, None) => zoom,
     };
+    redraw(backend, workspace);
+}
+
+fn show_floating<B: Backend>(backend: &mut B, workspace: &Workspace<B::Window>, window: B::Window) {
+    let dim = scratchpad::float_dimensions(&workspace.root.dim);
+    backend.show_window(window);
+    backend.set_window_pos(window, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+}
+
+fn untile_window<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>, window: B::Window) {
+    let had_focus = workspace.focus == Some(window);
+    let sibling = tile::sibling(&mut workspace.root, window);
+    workspace.remove_window(window);
+    workspace.retile_overflowed();
+    rearrange(backend, workspace);
 
+    // focus goes back to the window used before this one, or to whatever took its place
+    if had_focus {
+        if let Some(next) = workspace.focus.or(sibling) {
+            focus_window(backend, workspace, next);
+        }
+    }
+}
+
+// A tiled window resized with the mouse takes the separators under its edges along, and one
+// dropped on another window is swapped with it or put next to it. Anything else it did is
+// undone by the redraw. A floating window keeps wherever it was put.
+fn window_moved<B: Backend>(
+    backend: &mut B,
+    workspace: &mut Workspace<B::Window>,
+    window: B::Window,
+    dim: tile::Dimensions,
+    (x, y): (i32, i32),
+) {
+    if let Some(i) = workspace.floating_index(window) {
+        let node = &mut workspace.floating[i];
+        if node.node_type == tile::NodeType::Window(window) {
+            node.dim = dim.clone();
+            if let Some((_, placed)) = workspace.placed.iter_mut().find(|(placed, _)| *placed == window) {
+                *placed = dim;
+            }
+        }
+        return;
+    }
+
+    if workspace.zoom.is_none() {
+        let old = match tile::find_node(&mut workspace.root, window) {
+            Some(node) => node.dim.clone(),
+            None => return,
+        };
+        // whatever happens next the window has to be put somewhere, even where it was
+        workspace.placed.retain(|(placed, _)| *placed != window);
+        // the window was drawn inside its node, go back to the node's rect
+        let dim = monitor::grow(&dim, &monitor::gap_insets(&old, &workspace.root.dim, workspace.gap()));
+        let resized = old.x.1 != dim.x.1 || old.y.1 != dim.y.1;
+        if resized {
+            tile::drag_edges(&mut workspace.root, &old, &dim, EDGE_TOLERANCE);
+            workspace.retile_overflowed();
+        } else if let Some(target) = tile::window_at(&workspace.root, x, y).filter(|target| *target != window) {
+            let zone = tile::find_node(&mut workspace.root, target).map(|node| tile::drop_zone(&node.dim, x, y));
+            if let Some(zone) = zone {
+                tile::drop_window(&mut workspace.root, window, target, zone);
+                rearrange(backend, workspace);
+                return;
+            }
+        }
+    }
+    redraw(backend, workspace);
+}
+
+// Redraws the workspace after windows were added to or removed from its tree.
+fn rearrange<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>) {
+    // paths into the tree don't survive it changing shape
+    workspace.selection = None;
+    if workspace.auto_balance {
+        tile::balance(&mut workspace.root);
+    }
+    redraw(backend, workspace);
+}
+
+fn focus_changed<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>, window: B::Window) {
+    if workspace.focus != Some(window) {
+        workspace.selection = None;
+    }
+    change_focused_window(workspace, window);
+    // moving to another tiled window brings the rest of the layout back
+    if workspace.zoom.is_some_and(|zoom| workspace.focus == Some(window) && zoom.window != window) {
+        workspace.zoom = None;
+        redraw(backend, workspace);
+    }
+}
+
+fn change_focused_window<W: internal::Handle>(workspace: &mut Workspace<W>, window: W) {
+    if workspace.contains(window) {
+        workspace.remember_focus(window);
+    }
+}
+
+// Focuses a window ourselves instead of waiting for the system to tell us about it.
+fn focus_window<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>, window: B::Window) {
+    backend.focus_window(window);
+    focus_changed(backend, workspace, window);
+}
+
+fn tile_existing_windows<W: internal::Handle>(root: &mut tile::Node<W>, mut windows: Vec<W>) {
     while !windows.is_empty() {
-        tile::tile(&mut root, tile::Orientation::Horizontal, windows.remove(0));
+        tile::tile(root, tile::Orientation::Horizontal, windows.remove(0));
+    }
+}
+
+// Positions the workspace's windows, or only the zoomed one while a zoom is on. The other
+// windows are left where they are underneath it. Windows already where they belong aren't
+// touched, the rest are moved together.
+fn redraw<B: Backend>(backend: &mut B, workspace: &mut Workspace<B::Window>) {
+    match workspace.zoom {
+        Some(zoom) => {
+            let dim = match zoom.area {
+                ZoomArea::Workspace => workspace.root.dim.clone(),
+                ZoomArea::Monitor => match &workspace.monitor {
+                    Some(monitor) => monitor.area.clone(),
+                    None => backend.get_monitor_dimensions(),
+                },
+            };
+            backend.show_window(zoom.window);
+            backend.set_window_pos(zoom.window, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+            workspace.placed = vec![(zoom.window, dim)];
+        }
+        None => {
+            fit(backend, workspace);
+            let mut placed = Vec::new();
+            layout_nodes(&workspace.root, &workspace.root.dim, workspace.gap(), &mut placed);
+            for node in &workspace.floating {
+                layout_nodes(node, &node.dim, 0, &mut placed);
+            }
+            let changed = tile::changed(&workspace.placed, &placed);
+            for (window, _) in &changed {
+                backend.show_window(*window);
+            }
+            if !changed.is_empty() {
+                backend.set_window_positions(&changed);
+            }
+            workspace.placed = placed;
+        }
+    }
+}
+
+// Lays the workspace out again within the size limits of its windows. With the float overflow
+// policy, windows that still don't fit are floated one at a time, the one needing the most room
+// first, until the rest do.
+fn fit<B: Backend>(backend: &B, workspace: &mut Workspace<B::Window>) {
+    // a window is drawn inside its gaps, so its node needs about that much more room
+    let gap = workspace.gap();
+    // asking a window can take a while, so each is only asked once
+    for window in workspace.windows() {
+        if !workspace.hints.iter().any(|(w, _)| *w == window) {
+            workspace.hints.push((window, backend.get_size_hints(window)));
+        }
     }
+    let hints: Vec<(B::Window, tile::SizeHints)> = workspace
+        .hints
+        .iter()
+        .map(|(window, hints)| {
+            let window = *window;
+            let grow = |(width, height): (i32, i32)| (width.saturating_add(gap), height.saturating_add(gap));
+            (window, tile::SizeHints { min: grow(hints.min), max: grow(hints.max) })
+        })
+        .collect();
+    let hints_of = |window: &B::Window| {
+        hints
+            .iter()
+            .find(|(w, _)| w == window)
+            .map(|(_, hints)| *hints)
+            .unwrap_or_default()
+    };
 
-    root
+    for node in &mut workspace.floating {
+        tile::fit(node, &hints_of);
+    }
+    loop {
+        let overflowing = tile::fit(&mut workspace.root, &hints_of);
+        if workspace.overflow == tile::Overflow::Shrink {
+            return;
+        }
+        let area = |window: &B::Window| {
+            let min = hints_of(window).min;
+            min.0 as i64 * min.1 as i64
+        };
+        let window = match overflowing.into_iter().max_by_key(area) {
+            Some(window) => window,
+            None => return,
+        };
+        // a window on its own has nothing to make room for
+        let spot = tile::spot(&workspace.root, &window);
+        let node = tile::find_path(&workspace.root, &window)
+            .filter(|path| !path.is_empty())
+            .and_then(|path| tile::take(&mut workspace.root, &path));
+        let (mut node, spot) = match (node, spot) {
+            (Some(node), Some(spot)) => (node, spot),
+            _ => return,
+        };
+        workspace.overflowed.push((window, spot));
+        node.dim = overflow_dimensions(&workspace.root.dim, hints_of(&window).min);
+        workspace.floating.push(node);
+        workspace.selection = None;
+    }
 }
 
-fn redraw_nodes(root: &tile::Node<windef::HWND>) {
+// Where a window floated for not fitting goes: centered like any floating window, but at least
+// as big as it has to be.
+fn overflow_dimensions(area: &tile::Dimensions, (min_width, min_height): (i32, i32)) -> tile::Dimensions {
+    let dim = scratchpad::float_dimensions(area);
+    let width = dim.x.1.max(min_width).min(area.x.1);
+    let height = dim.y.1.max(min_height).min(area.y.1);
+    tile::Dimensions {
+        x: (area.x.0 + (area.x.1 - width) / 2, width),
+        y: (area.y.0 + (area.y.1 - height) / 2, height),
+    }
+}
+
+// Adds where each window under root goes to placed. Windows are drawn gap pixels inside their
+// node, area is the space the whole tree is in.
+fn layout_nodes<W: Copy>(root: &tile::Node<W>, area: &tile::Dimensions, gap: i32, placed: &mut Vec<(W, tile::Dimensions)>) {
     match &root.node_type {
-        tile::NodeType::Separator(_, left_child, right_child) => {
-            redraw_nodes(left_child);
-            redraw_nodes(right_child);
+        tile::NodeType::Separator(_, _, left_child, right_child) => {
+            layout_nodes(left_child, area, gap, placed);
+            layout_nodes(right_child, area, gap, placed);
         }
         tile::NodeType::Window(hwnd) => {
-            internal::show_window(hwnd.clone());
-            internal::set_window_pos(
-                hwnd.clone(),
-                root.dim.x.0,
-                root.dim.y.0,
-                root.dim.x.1,
-                root.dim.y.1,
-            );
-        }
-        tile::NodeType::Empty => return,
+            let dim = monitor::work_area(&root.dim, &monitor::gap_insets(&root.dim, area, gap));
+            placed.push((*hwnd, dim));
+        }
+        tile::NodeType::Placeholder(_) | tile::NodeType::Empty => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::mock::{Call, MockBackend};
+    use crate::internal::WindowEvent;
+    use crate::monitor::{Edges, Monitor, Reservation};
+    use clock::FakeClock;
+    use std::time::Duration;
+
+    // Replays a small trace on a 1920x1080 screen. Windows only need their id, the rest of
+    // their info can be left out.
+    fn replay(trace: &str) -> (State<usize>, MockBackend) {
+        let (state, backend, _) = replay_with_clock(trace);
+        (state, backend)
+    }
+
+    // The same, keeping hold of the clock the replay ran on to move it along afterwards.
+    fn replay_with_clock(trace: &str) -> (State<usize>, MockBackend, FakeClock) {
+        let trace = format!("0\tdimensions\t0\t1920\t0\t1080\n{}", trace);
+        let clock = FakeClock::default();
+        let (state, backend) = replay::replay(&record::parse(&trace).unwrap(), clock.clone());
+        (state, backend, clock)
+    }
+
+    // Windows 2 and 3 side by side, 3 opened last, followed by the test's own lines.
+    fn two_windows_trace(extra: &str) -> String {
+        format!("0\twindow\t2\n0\tactive\t2\n10\tcommand\torientation toggle\n20\tcreated\t3\n{}", extra)
+    }
+
+    #[test]
+    fn template_that_cannot_be_loaded_should_be_reported_and_leave_layout_alone() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+");
+        let root = state.workspace().root.clone();
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, "template load no such template.json".parse().unwrap());
+
+        assert_eq!(root, state.workspace().root);
+        assert!(backend.calls.is_empty());
+        assert_eq!(1, state.errors.len());
+        assert!(state.errors[0].starts_with("could not load template"));
+    }
+
+    #[test]
+    fn preselection_should_be_used_once_then_fall_back_to_policy() {
+        let (state, _) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcommand\tpresel left 0.25
+20\tcreated\t2
+30\tcommand\tinsert root
+40\tcreated\t3
+");
+
+        // 2 went left of 1, then 3 was added below both
+        let root = &state.workspace().root;
+        if let tile::NodeType::Separator(tile::Orientation::Horizontal, _, top, bottom) = &root.node_type {
+            if let tile::NodeType::Separator(tile::Orientation::Vertical, _, left, right) = &top.node_type {
+                assert_eq!(tile::NodeType::Window(2), left.node_type);
+                assert_eq!(tile::Dimensions { x: (0, 480), y: (0, 540) }, left.dim);
+                assert_eq!(tile::NodeType::Window(1), right.node_type);
+            } else {
+                panic!("Top is not a vertical separator");
+            }
+            assert_eq!(tile::NodeType::Window(3), bottom.node_type);
+        } else {
+            panic!("Root is not a horizontal separator");
+        }
+        assert_eq!(None, state.workspace().preselection);
+    }
+
+    #[test]
+    fn auto_balance_should_balance_tree_on_every_change() {
+        let (mut state, _) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcommand\tbalance auto
+20\tcommand\tinsert split
+30\tcommand\torientation toggle
+40\tcreated\t2
+50\tfocus\t2
+60\tcreated\t3
+");
+
+        // without balancing 2 and 3 would only get a quarter of the screen each
+        let root = &mut state.workspace_mut().root;
+        assert_eq!(640, tile::find_node(root, 1).unwrap().dim.x.1);
+        assert_eq!(640, tile::find_node(root, 2).unwrap().dim.x.1);
+        assert_eq!(640, tile::find_node(root, 3).unwrap().dim.x.1);
+    }
+
+    #[test]
+    fn zoom_should_only_position_zoomed_window_and_restore_layout_after() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcreated\t2
+11\tfocus\t2
+20\tcommand\torientation toggle
+30\tcreated\t3
+40\tfocus\t1
+");
+        state.workspace_mut().monitor.as_mut().unwrap().area = tile::Dimensions { x: (0, 1920), y: (0, 1200) };
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, Command::ToggleFullscreen);
+        assert_eq!(vec![Call::Show(1), Call::SetPos(1, 0, 0, 1920, 1200)], backend.calls);
+
+        // switching to zoom keeps the window but shrinks it to the tiling area
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::ToggleZoom);
+        assert_eq!(vec![Call::Show(1), Call::SetPos(1, 0, 0, 1920, 1080)], backend.calls);
+
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::ToggleZoom);
+        assert_eq!(None, state.workspace().zoom);
+        assert_eq!(6, backend.calls.len());
+        assert!(backend.calls.contains(&Call::SetPos(1, 0, 0, 1920, 540)));
+    }
+
+    #[test]
+    fn new_window_should_end_zoom() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\tactive\t1
+");
+        run_command(&mut backend, &mut state, Command::ToggleZoom);
+
+        handle_event(&mut backend, &mut state, WindowEvent::Created(4));
+
+        assert_eq!(None, state.workspace().zoom);
+        assert!(tile::find_node(&mut state.workspace_mut().root, 4).is_some());
+    }
+
+    #[test]
+    fn scratchpad_should_take_window_out_of_tree_and_float_it_when_summoned() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\tactive\t1
+");
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, Command::MoveToScratchpad);
+        assert!(tile::find_node(&mut state.workspace_mut().root, 1).is_none());
+        assert_eq!(Some(&Call::Hide(1)), backend.calls.last());
+
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::ToggleScratchpad(None));
+        assert_eq!(vec![Call::Show(1), Call::SetPos(1, 480, 270, 960, 540)], backend.calls);
+
+        run_command(&mut backend, &mut state, Command::ToggleScratchpad(None));
+        assert_eq!(Some(&Call::Hide(1)), backend.calls.last());
+    }
+
+    #[test]
+    fn named_scratchpad_should_float_the_window_it_launched() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+");
+        state.scratchpad.named = vec![scratchpad::NamedScratchpad {
+            name: "notes".to_string(),
+            criteria: internal::Criteria {
+                class: Some("Notepad".to_string()),
+                ..Default::default()
+            },
+            program: "notepad.exe".to_string(),
+        }];
+        backend.info.insert(5, internal::WindowInfo {
+            class: "Notepad".to_string(),
+            ..Default::default()
+        });
+
+        run_command(&mut backend, &mut state, Command::ToggleScratchpad(Some("notes".to_string())));
+        assert_eq!(Some(&Call::Launch("notepad.exe".to_string())), backend.calls.last());
+        handle_event(&mut backend, &mut state, WindowEvent::Created(5));
+
+        assert!(tile::find_node(&mut state.workspace_mut().root, 5).is_none());
+        assert_eq!(Some(&Call::SetPos(5, 480, 270, 960, 540)), backend.calls.last());
+    }
+
+    #[test]
+    fn named_scratchpad_should_launch_again_after_launch_failed() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+");
+        state.scratchpad.named = vec![scratchpad::NamedScratchpad {
+            name: "notes".to_string(),
+            criteria: internal::Criteria {
+                class: Some("Notepad".to_string()),
+                ..Default::default()
+            },
+            program: r#""C:\Program Files\Notes\notes.exe""#.to_string(),
+        }];
+        backend.missing.push(r#""C:\Program Files\Notes\notes.exe""#.to_string());
+
+        run_command(&mut backend, &mut state, Command::ToggleScratchpad(Some("notes".to_string())));
+        assert_eq!(1, state.errors.len());
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::ToggleScratchpad(Some("notes".to_string())));
+
+        assert_eq!(vec![Call::Launch(r#""C:\Program Files\Notes\notes.exe""#.to_string())], backend.calls);
+    }
+
+    #[test]
+    fn closing_focused_window_should_focus_previous_one() {
+        let (state, backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\twindow\t3
+0\tactive\t3
+10\tfocus\t1
+20\tdestroyed\t1
+");
+
+        assert_eq!(Some(&Call::Focus(3)), backend.calls.last());
+        assert_eq!(Some(3), state.workspace().focus);
+        assert_eq!(vec![3], state.workspace().history);
+    }
+
+    #[test]
+    fn closing_focused_window_without_history_should_focus_sibling() {
+        let (state, backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\twindow\t3
+0\tactive\t2
+20\tdestroyed\t2
+");
+
+        // 1 and 2 share a container, with 3 below them
+        assert_eq!(Some(&Call::Focus(1)), backend.calls.last());
+        assert_eq!(Some(1), state.workspace().focus);
+    }
+
+    #[test]
+    fn focus_commands_should_cycle_tree_order_and_history() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\twindow\t3
+0\tactive\t2
+10\tfocus\t1
+");
+        // tree order is 1, 2, 3 and window 1 has focus after window 2
+
+        run_command(&mut backend, &mut state, Command::FocusLast);
+        assert_eq!(Some(2), state.workspace().focus);
+        run_command(&mut backend, &mut state, Command::FocusNext);
+        assert_eq!(Some(3), state.workspace().focus);
+        run_command(&mut backend, &mut state, Command::FocusNext);
+        assert_eq!(Some(1), state.workspace().focus);
+        run_command(&mut backend, &mut state, Command::FocusPrev);
+        assert_eq!(Some(3), state.workspace().focus);
+        assert_eq!(Some(&Call::Focus(3)), backend.calls.last());
+        assert_eq!(vec![3, 1, 2], state.workspace().history);
+    }
+
+    #[test]
+    fn focus_parent_should_let_commands_act_on_whole_container() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcreated\t2
+11\tfocus\t2
+20\tcommand\torientation toggle
+30\tcreated\t3
+");
+        // window 2 is focused, 2 and 3 share the bottom half
+
+        run_command(&mut backend, &mut state, Command::FocusParent);
+        assert_eq!(Some(vec![1]), state.workspace().selection);
+        run_command(&mut backend, &mut state, Command::Move(tile::Direction::Up));
+        assert_eq!(Some(vec![0]), state.workspace().selection);
+        assert_eq!(vec![2, 3, 1], tile::windows(&state.workspace().root));
+
+        run_command(&mut backend, &mut state, Command::FocusChild);
+        assert_eq!(None, state.workspace().selection);
+    }
+
+    #[test]
+    fn send_to_workspace_should_move_container_and_hide_its_windows() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcreated\t2
+11\tfocus\t2
+20\tcommand\torientation toggle
+30\tcreated\t3
+40\tcommand\tfocus parent
+");
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, Command::SendToWorkspace("2".to_string()));
+
+        assert_eq!(vec![1], tile::windows(&state.workspace().root));
+        assert!(backend.calls.contains(&Call::Hide(2)));
+        assert!(backend.calls.contains(&Call::Hide(3)));
+        assert_eq!(Some(&Call::Focus(1)), backend.calls.last());
+        assert_eq!("2", state.workspaces[1].name);
+        assert_eq!(vec![2, 3], tile::windows(&state.workspaces[1].root));
+
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::SwitchWorkspace("2".to_string()));
+        assert_eq!(Call::Hide(1), backend.calls[0]);
+        assert!(backend.calls.contains(&Call::SetPos(3, 961, 0, 960, 1080)));
+        assert_eq!(Some(&Call::Focus(2)), backend.calls.last());
+    }
+
+    #[test]
+    fn float_toggle_should_take_window_out_of_tree_and_back() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\twindow\t2
+0\twindow\t3
+0\tactive\t1
+");
+
+        run_command(&mut backend, &mut state, Command::ToggleFloating);
+        assert_eq!(vec![2, 3], tile::windows(&state.workspace().root));
+        assert_eq!(Some(&Call::SetPos(1, 480, 270, 960, 540)), backend.calls.last());
+
+        run_command(&mut backend, &mut state, Command::ToggleFloating);
+        assert!(state.workspace().floating.is_empty());
+        assert_eq!(vec![2, 3, 1], tile::windows(&state.workspace().root));
+    }
+
+    #[test]
+    fn dragging_window_edge_should_move_separator() {
+        let (_, backend) = replay(&two_windows_trace("\
+30\tmoved\t2\t0\t1200\t0\t1080\t1200\t500
+"));
+
+        assert_eq!(Some(&Call::SetPos(3, 1201, 0, 720, 1080)), backend.calls.last());
+    }
+
+    #[test]
+    fn moving_tiled_window_should_snap_it_back() {
+        let (_, backend) = replay(&two_windows_trace("\
+30\tmoved\t3\t100\t960\t0\t1080\t1100\t10
+"));
+
+        assert_eq!(Some(&Call::SetPos(3, 961, 0, 960, 1080)), backend.calls.last());
+    }
+
+    #[test]
+    fn dropping_window_on_center_of_another_should_swap_them() {
+        let (state, backend) = replay(&two_windows_trace("\
+30\tmoved\t2\t800\t960\t0\t1080\t1440\t540
+"));
+
+        assert_eq!(vec![3, 2], tile::windows(&state.workspace().root));
+        assert_eq!(Some(&Call::SetPos(2, 961, 0, 960, 1080)), backend.calls.last());
+    }
+
+    #[test]
+    fn dropping_window_on_edge_of_another_should_split_it() {
+        let (_, backend) = replay(&two_windows_trace("\
+30\tmoved\t2\t800\t960\t0\t1080\t1440\t1070
+"));
+
+        let last = &backend.calls[backend.calls.len() - 4..];
+        assert_eq!(
+            &[Call::Show(3), Call::Show(2), Call::SetPos(3, 0, 0, 1920, 540), Call::SetPos(2, 0, 541, 1920, 540)],
+            last
+        );
+    }
+
+    // delayed work runs off this clock from here on, so tests can move time along
+    #[test]
+    fn resting_cursor_on_window_should_focus_it_after_delay() {
+        let (mut state, mut backend, clock) = replay_with_clock(&two_windows_trace(""));
+        state.hover = Hover::new(Some(Duration::from_millis(200)));
+        backend.calls.clear();
+
+        handle_event(&mut backend, &mut state, WindowEvent::MouseMoved(300, 500));
+        clock.advance(Duration::from_millis(150));
+        handle_event(&mut backend, &mut state, WindowEvent::MouseMoved(1500, 500));
+        clock.advance(Duration::from_millis(150));
+        run_timers(&mut backend, &mut state, None);
+        assert!(backend.calls.is_empty());
+
+        clock.advance(Duration::from_millis(50));
+        run_timers(&mut backend, &mut state, None);
+        assert_eq!(vec![Call::Focus(3)], backend.calls);
+        assert_eq!(Some(3), state.workspace().focus);
+    }
+
+    #[test]
+    fn mouse_should_follow_focus_changed_by_commands() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        state.mouse_follows_focus = true;
+        backend.calls.clear();
+
+        handle_event(&mut backend, &mut state, WindowEvent::Command(Command::FocusNext));
+        handle_event(&mut backend, &mut state, WindowEvent::Command(Command::Balance));
+
+        assert_eq!(Call::MoveCursor(1441, 540), backend.calls[1]);
+        assert_eq!(1, backend.calls.iter().filter(|call| matches!(call, Call::MoveCursor(..))).count());
+    }
+
+    #[test]
+    fn mouse_should_follow_focus_changed_by_system_unless_already_on_window() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        state.mouse_follows_focus = true;
+        backend.cursor = Some((480, 540));
+        backend.calls.clear();
+
+        // alt+tab to 3 while the cursor is on 2
+        handle_event(&mut backend, &mut state, WindowEvent::FocusChanged(3));
+        assert_eq!(vec![Call::MoveCursor(1441, 540)], backend.calls);
+
+        // clicking 2 focuses it with the cursor already there
+        backend.cursor = Some((100, 100));
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::FocusChanged(2));
+        assert!(backend.calls.is_empty());
+    }
+
+    #[test]
+    fn minimized_window_should_leave_layout_and_come_back_to_its_place() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        backend.calls.clear();
+
+        handle_event(&mut backend, &mut state, WindowEvent::Hidden(2));
+        assert_eq!(vec![3], tile::windows(&state.workspace().root));
+        assert!(!backend.calls.contains(&Call::Show(2)));
+        assert_eq!(Some(&Call::Focus(3)), backend.calls.last());
+
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::Shown(2));
+        assert_eq!(vec![2, 3], tile::windows(&state.workspace().root));
+        assert!(backend.calls.contains(&Call::SetPos(2, 0, 0, 960, 1080)));
+        assert!(state.workspace().minimized.is_empty());
+    }
+
+    #[test]
+    fn hiding_window_outside_layout_should_change_nothing() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t2
+0\twindow\t3
+0\tactive\t2
+10\tcommand\tscratchpad move
+");
+        backend.calls.clear();
+
+        handle_event(&mut backend, &mut state, WindowEvent::Hidden(2));
+        handle_event(&mut backend, &mut state, WindowEvent::Shown(2));
+        handle_event(&mut backend, &mut state, WindowEvent::Shown(3));
+
+        assert!(backend.calls.is_empty());
+        assert!(state.workspace().minimized.is_empty());
+    }
+
+    #[test]
+    fn window_restored_on_other_workspace_should_join_current_one() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t2
+0\twindow\t3
+0\tactive\t2
+10\thidden\t3
+20\tcommand\tworkspace 2
+");
+
+        handle_event(&mut backend, &mut state, WindowEvent::Shown(3));
+        assert_eq!(vec![3], tile::windows(&state.workspace().root));
+        assert!(state.workspaces[0].minimized.is_empty());
+
+        handle_event(&mut backend, &mut state, WindowEvent::Hidden(3));
+        handle_event(&mut backend, &mut state, WindowEvent::Destroyed(3));
+        assert!(state.workspace().minimized.is_empty());
+    }
+
+    #[test]
+    fn late_hidden_event_after_switching_back_should_keep_window_tiled() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t2
+0\tactive\t2
+10\tcreated\t3
+20\tcommand\tworkspace 2
+30\tcommand\tworkspace 1
+40\thidden\t2
+40\thidden\t3
+");
+        assert_eq!(vec![2, 3], tile::windows(&state.workspace().root));
+        assert!(state.workspace().minimized.is_empty());
+
+        // the user minimizing it afterwards still counts
+        handle_event(&mut backend, &mut state, WindowEvent::Hidden(2));
+        assert_eq!(vec![3], tile::windows(&state.workspace().root));
+        assert_eq!(vec![2], state.workspace().minimized.iter().map(|(window, _)| *window).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn coalescing_should_give_the_same_tree_as_handling_every_event() {
+        let trace = "\
+0\twindow\t1
+0\twindow\t2
+0\tactive\t1
+";
+        let events = vec![
+            WindowEvent::FocusChanged(2),
+            WindowEvent::Created(3),
+            WindowEvent::FocusChanged(1),
+            WindowEvent::Created(4),
+        ];
+        let (mut direct, mut backend) = replay(trace);
+        for event in events.clone() {
+            handle_event(&mut backend, &mut direct, event);
+        }
+
+        let (mut coalesced, mut backend, clock) = replay_with_clock(trace);
+        let mut coalescer = Coalescer::new(Duration::from_millis(30));
+        for event in events {
+            coalescer.push(event, &mut coalesced.timers);
+        }
+        clock.advance(Duration::from_millis(30));
+        assert_eq!(vec![Timer::Coalesce], coalesced.timers.take_due());
+        for event in coalescer.take() {
+            handle_event(&mut backend, &mut coalesced, event);
+        }
+
+        // 3 split 2 and 4 split 1, which only works if neither focus change was dropped
+        assert_eq!(direct.workspace().root, coalesced.workspace().root);
+        assert_eq!(vec![1, 4, 2, 3], tile::windows(&coalesced.workspace().root));
+    }
+
+    fn monitor(id: &str, x: i32, width: i32, height: i32) -> Monitor {
+        let area = tile::Dimensions { x: (x, width), y: (0, height) };
+        Monitor { id: id.to_string(), work_area: area.clone(), area, scale: 1.0 }
+    }
+
+    #[test]
+    fn start_should_keep_reserved_edges_out_of_layout() {
+        let mut backend = MockBackend::new(tile::Dimensions { x: (0, 1920), y: (0, 1040) });
+        backend.windows.push(1);
+        let reserved = Reservation { monitor: "1".to_string(), edges: Edges { top: 30, ..Default::default() } };
+
+        let config = Config { reservations: vec![reserved], ..Default::default() };
+
+        let state = start(&mut backend, None, &config, Box::new(FakeClock::default()));
+
+        assert_eq!(tile::Dimensions { x: (0, 1920), y: (30, 1010) }, state.workspace().root.dim);
+        assert_eq!(Some(&Call::SetPos(1, 0, 30, 1920, 1010)), backend.calls.last());
+    }
+
+    #[test]
+    fn reservations_should_stay_with_their_monitor_when_another_is_disconnected() {
+        let laptop = monitor("\\\\.\\DISPLAY1", 0, 1920, 1080);
+        let external = monitor("\\\\.\\DISPLAY2", 1920, 2560, 1440);
+        let mut backend = MockBackend::new(laptop.area.clone());
+        backend.monitors = vec![laptop.clone(), external.clone()];
+        backend.windows.push(1);
+        let config = Config::parse("reserve 1 top=30\nreserve 2 left=40").unwrap();
+
+        let mut state = start(&mut backend, None, &config, Box::new(FakeClock::default()));
+        assert_eq!(tile::Dimensions { x: (0, 1920), y: (30, 1050) }, state.workspace().root.dim);
+
+        // the laptop is closed, the external monitor is the only one left and keeps its own edges
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![external.clone()]));
+        assert_eq!(tile::Dimensions { x: (1960, 2520), y: (0, 1440) }, state.workspace().root.dim);
+
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![laptop, external]));
+        assert_eq!(tile::Dimensions { x: (0, 1920), y: (30, 1050) }, state.workspace().root.dim);
+    }
+
+    #[test]
+    fn work_area_change_should_lay_out_every_workspace_again() {
+        let (mut state, mut backend) = replay(&two_windows_trace("\
+30\tcommand\tworkspace 2
+40\tcommand\tworkspace 1
+"));
+        state.reservations = vec![Reservation { monitor: "1".to_string(), edges: Edges { top: 30, ..Default::default() } }];
+        backend.calls.clear();
+
+        let mut primary = monitor("1", 0, 1920, 1080);
+        primary.work_area.y.1 = 1040;
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![primary]));
+
+        assert_eq!(
+            vec![Call::Show(2), Call::Show(3), Call::SetPos(2, 0, 30, 960, 1010), Call::SetPos(3, 961, 30, 960, 1010)],
+            backend.calls
+        );
+        assert_eq!(tile::Dimensions { x: (0, 1920), y: (30, 1010) }, state.workspaces[1].root.dim);
+    }
+
+    #[test]
+    fn workspaces_should_leave_removed_monitor_and_come_back_to_it() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t2
+0\twindow\t3
+0\tactive\t2
+10\tcommand\tfloat toggle
+");
+        let laptop = monitor("1", 0, 1920, 1080);
+        let external = monitor("2", 1920, 3840, 2160);
+
+        // the laptop is closed, the external monitor takes over as primary
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![external.clone()]));
+        assert_eq!(Some(external.clone()), state.workspace().monitor);
+        assert_eq!(Some("1".to_string()), state.workspace().home);
+        assert_eq!(tile::Dimensions { x: (1920, 3840), y: (0, 2160) }, state.workspace().root.dim);
+        assert_eq!(tile::Dimensions { x: (2880, 1920), y: (540, 1080) }, state.workspace().floating[0].dim);
+        assert!(backend.calls.contains(&Call::SetPos(3, 1920, 0, 3840, 2160)));
+
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![external, laptop.clone()]));
+        assert_eq!(Some(laptop), state.workspace().monitor);
+        assert_eq!(tile::Dimensions { x: (0, 1920), y: (0, 1080) }, state.workspace().root.dim);
+        assert_eq!(tile::Dimensions { x: (480, 960), y: (270, 540) }, state.workspace().floating[0].dim);
+
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(Vec::new()));
+        assert!(backend.calls.is_empty());
+    }
+
+    #[test]
+    fn gaps_should_be_scaled_for_monitor_and_follow_dpi_changes() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        state.workspace_mut().gaps = 8;
+        let mut primary = monitor("1", 0, 1920, 1080);
+        primary.scale = 1.5;
+        backend.calls.clear();
+
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![primary.clone()]));
+        assert!(backend.calls.contains(&Call::SetPos(2, 12, 12, 942, 1056)));
+        assert!(backend.calls.contains(&Call::SetPos(3, 967, 12, 942, 1056)));
+
+        // moving the window to a monitor with another scale sizes the gaps again
+        primary.scale = 2.0;
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::MonitorsChanged(vec![primary]));
+        assert!(backend.calls.contains(&Call::SetPos(2, 16, 16, 936, 1048)));
+    }
+
+    #[test]
+    fn dragging_window_with_gaps_should_move_separator_by_what_was_dragged() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        state.workspace_mut().gaps = 10;
+
+        // the window is drawn inside its gaps, so a pure move isn't mistaken for a resize
+        let dim = tile::Dimensions { x: (110, 945), y: (10, 1060) };
+        handle_event(&mut backend, &mut state, WindowEvent::Moved(2, dim, (5, 5)));
+        assert!(backend.calls.contains(&Call::SetPos(2, 10, 10, 945, 1060)));
+
+        let dim = tile::Dimensions { x: (10, 1180), y: (10, 1060) };
+        handle_event(&mut backend, &mut state, WindowEvent::Moved(2, dim, (1190, 500)));
+        assert_eq!(Some(&Call::SetPos(3, 1201, 10, 710, 1060)), backend.calls.last());
+    }
+
+    #[test]
+    fn window_with_minimum_size_should_take_room_from_its_neighbour() {
+        let (state, backend) = replay("\
+0\twindow\t2
+0\tactive\t2
+10\tcommand\torientation toggle
+20\thints\t3\t1200\t500\t2147483647\t2147483647
+20\tcreated\t3
+");
+
+        assert!(backend.calls.contains(&Call::SetPos(2, 0, 0, 720, 1080)));
+        assert!(backend.calls.contains(&Call::SetPos(3, 721, 0, 1200, 1080)));
+        // the split itself is left alone for when the window gets smaller again
+        assert!(matches!(state.workspace().root.node_type, tile::NodeType::Separator(_, ratio, ..) if ratio == 0.5));
+    }
+
+    #[test]
+    fn overflow_should_float_window_needing_most_room_or_shrink_both() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t2
+0\thints\t2\t1200\t500\t2147483647\t2147483647
+0\tactive\t2
+10\tcommand\torientation toggle
+20\thints\t3\t1000\t500\t2147483647\t2147483647
+20\tcreated\t3
+");
+        assert!(backend.calls.contains(&Call::SetPos(2, 0, 0, 1047, 1080)));
+        assert!(backend.calls.contains(&Call::SetPos(3, 1048, 0, 873, 1080)));
+
+        state.workspace_mut().overflow = tile::Overflow::Float;
+        backend.calls.clear();
+        run_command(&mut backend, &mut state, Command::Balance);
+        assert!(backend.calls.contains(&Call::SetPos(2, 360, 270, 1200, 540)));
+        assert!(backend.calls.contains(&Call::SetPos(3, 0, 0, 1920, 1080)));
+        assert_eq!(vec![3], tile::windows(&state.workspace().root));
+
+        // closing 3 makes room for 2 again
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::Destroyed(3));
+        assert_eq!(vec![2], tile::windows(&state.workspace().root));
+        assert!(state.workspace().floating.is_empty());
+        assert_eq!(Some(&Call::SetPos(2, 0, 0, 1920, 1080)), backend.calls.iter().rev().find(|call| matches!(call, Call::SetPos(..))));
+    }
+
+    #[test]
+    fn size_hints_should_only_be_asked_again_once_window_moved_itself() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        backend.hints.insert(3, tile::SizeHints { min: (1200, 500), max: (i32::MAX, i32::MAX) });
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, Command::Balance);
+        assert!(backend.calls.is_empty());
+
+        // put back where it was placed, so nothing changed
+        handle_event(&mut backend, &mut state, WindowEvent::Relocated(3));
+        run_command(&mut backend, &mut state, Command::Balance);
+        assert!(backend.calls.is_empty());
+
+        backend.rects.insert(3, tile::Dimensions { x: (500, 900), y: (0, 1080) });
+        handle_event(&mut backend, &mut state, WindowEvent::Relocated(3));
+        run_command(&mut backend, &mut state, Command::Balance);
+        assert!(backend.calls.contains(&Call::SetPos(3, 721, 0, 1200, 1080)));
+    }
+
+    #[test]
+    fn reconcile_should_put_windows_back_until_they_keep_refusing() {
+        let (mut state, mut backend, clock) = replay_with_clock(&two_windows_trace(""));
+        run_timers(&mut backend, &mut state, None);
+        // 2 moved itself after being placed, 3 won't move at all
+        let moved = tile::Dimensions { x: (100, 500), y: (100, 500) };
+        backend.rects.insert(2, moved.clone());
+        backend.rects.insert(3, moved);
+        backend.stuck.push(3);
+        backend.calls.clear();
+
+        clock.advance(reconcile::DELAY / 2);
+        run_timers(&mut backend, &mut state, None);
+        assert!(backend.calls.is_empty());
+        clock.advance(reconcile::DELAY / 2);
+        run_timers(&mut backend, &mut state, None);
+        assert_eq!(vec![Call::SetPos(2, 0, 0, 960, 1080), Call::SetPos(3, 961, 0, 960, 1080)], backend.calls);
+
+        for _ in 0..reconcile::ATTEMPTS {
+            clock.advance(reconcile::DELAY);
+            run_timers(&mut backend, &mut state, None);
+        }
+        let retries = backend.calls.iter().filter(|call| matches!(call, Call::SetPos(3, ..))).count();
+        assert_eq!(reconcile::ATTEMPTS as usize, retries);
+        assert_eq!(1, backend.calls.iter().filter(|call| matches!(call, Call::SetPos(2, ..))).count());
+        assert_eq!(vec![3], state.reconcile.refusing);
+    }
+
+    #[test]
+    fn enforce_layout_should_put_back_window_that_moved_itself() {
+        let (mut state, mut backend, clock) = replay_with_clock(&two_windows_trace(""));
+        run_timers(&mut backend, &mut state, None);
+        clock.advance(reconcile::DELAY);
+        run_timers(&mut backend, &mut state, None);
+
+        backend.rects.insert(2, tile::Dimensions { x: (100, 500), y: (100, 500) });
+        backend.calls.clear();
+        handle_event(&mut backend, &mut state, WindowEvent::Relocated(2));
+        clock.advance(reconcile::DELAY);
+        run_timers(&mut backend, &mut state, None);
+        assert!(backend.calls.is_empty());
+
+        state.reconcile.enforce = true;
+        handle_event(&mut backend, &mut state, WindowEvent::Relocated(2));
+        clock.advance(reconcile::DELAY);
+        run_timers(&mut backend, &mut state, None);
+        assert_eq!(vec![Call::SetPos(2, 0, 0, 960, 1080)], backend.calls);
+    }
+
+    #[test]
+    fn redraw_should_only_move_windows_whose_place_changed() {
+        let (mut state, mut backend) = replay(&two_windows_trace(""));
+        backend.calls.clear();
+
+        run_command(&mut backend, &mut state, Command::Balance);
+        assert!(backend.calls.is_empty());
+
+        // coming back to a workspace shows all of it again
+        run_command(&mut backend, &mut state, Command::SwitchWorkspace("2".to_string()));
+        run_command(&mut backend, &mut state, Command::SwitchWorkspace("1".to_string()));
+        let shown: Vec<_> = backend.calls.iter().filter(|call| matches!(call, Call::Show(_))).collect();
+        assert_eq!(vec![&Call::Show(2), &Call::Show(3)], shown);
+    }
+
+    fn app_info(window: usize) -> internal::WindowInfo {
+        internal::WindowInfo {
+            title: format!("window {}", window),
+            class: "Class".to_string(),
+            process: "app.exe".to_string(),
+        }
+    }
+
+    #[test]
+    fn start_should_adopt_windows_that_were_hidden_when_saved() {
+        let dim = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+        let mut first = Workspace::new("1", dim.clone());
+        tile::tile(&mut first.root, tile::Orientation::Vertical, 1);
+        let mut second = Workspace::new("2", dim.clone());
+        tile::tile(&mut second.root, tile::Orientation::Vertical, 2);
+        let mut saved = State::new(vec![first, second], Box::new(FakeClock::default()));
+        saved.scratchpad.add(3, None, false);
+        let layout = layout::capture(&saved, &app_info);
+        // only the visible window is enumerated, 2 and 3 are still around but hidden
+        let mut backend = MockBackend::new(dim);
+        backend.windows = vec![1];
+        for window in 1..=3 {
+            backend.info.insert(window, app_info(window));
+        }
+
+        let state = start(&mut backend, Some(&layout), &Config::default(), Box::new(FakeClock::default()));
+
+        assert_eq!(vec![1], state.workspaces[0].windows());
+        assert_eq!(vec![2], state.workspaces[1].windows());
+        assert_eq!(vec![3], state.scratchpad.windows.iter().map(|scratch| scratch.window).collect::<Vec<_>>());
+        assert_eq!(vec![Call::Hide(2), Call::Hide(3)], backend.calls[..2].to_vec());
+    }
+
+    #[test]
+    fn session_should_only_be_saved_again_once_layout_changed() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+");
+        let dir = std::env::temp_dir().join(format!("tiles10-loop-session-{}", std::process::id()));
+        let session = || Some(Session::new(dir.join("session.json")));
+
+        backend.events.extend(vec![WindowEvent::MouseMoved(5, 5), WindowEvent::Command(Command::Quit)]);
+        hook_and_loop(&mut backend, &Config::default(), &mut state, None, session(), |_| ()).unwrap();
+        assert!(!state.timers.is_scheduled(Timer::SaveSession));
+
+        backend.events.extend(vec![WindowEvent::Created(2), WindowEvent::Command(Command::Quit)]);
+        hook_and_loop(&mut backend, &Config::default(), &mut state, None, session(), |_| ()).unwrap();
+        assert!(state.timers.is_scheduled(Timer::SaveSession));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quit_should_show_every_managed_window() {
+        let (mut state, mut backend) = replay("\
+0\twindow\t1
+0\tactive\t1
+10\tcreated\t2
+20\tcommand\tsend 2
+30\tcreated\t3
+40\tcommand\tscratchpad move
+");
+        backend.calls.clear();
+        backend.events.push_back(WindowEvent::Command(Command::Quit));
+
+        hook_and_loop(&mut backend, &Config::default(), &mut state, None, None, |_| ()).unwrap();
+
+        let mut shown: Vec<_> = backend.calls.iter().filter_map(|call| match call {
+            Call::Show(window) => Some(*window),
+            _ => None,
+        }).collect();
+        shown.sort();
+        assert_eq!(vec![1, 2, 3], shown);
     }
 }
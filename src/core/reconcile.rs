@@ -0,0 +1,150 @@
+use super::timer::Timers;
+use super::Timer;
+use crate::tile;
+use std::time::Duration;
+
+// how long windows get to settle after being placed before they're checked
+pub const DELAY: Duration = Duration::from_millis(150);
+// how many times a window is put back before it's taken as refusing to go there
+pub const ATTEMPTS: u32 = 3;
+
+// Windows don't always end up where they're put: some won't take the size they're given, others
+// move themselves right after they open. Placed windows are checked once they had time to settle
+// and put back, a few times, before they're flagged as refusing.
+pub struct Reconcile<W> {
+    // re-apply the layout whenever a window moves itself, not only right after placing it
+    pub enforce: bool,
+    // windows that kept refusing their place, they're left alone from then on
+    pub refusing: Vec<W>,
+    // the placement last seen, to notice the layout being drawn again
+    seen: Vec<(W, tile::Dimensions)>,
+    attempts: Vec<(W, u32)>,
+}
+
+impl<W: Copy + PartialEq> Reconcile<W> {
+    pub fn new(enforce: bool) -> Reconcile<W> {
+        Reconcile { enforce, refusing: Vec::new(), seen: Vec::new(), attempts: Vec::new() }
+    }
+
+    // placed is where the last redraw put each window. A new placement gets checked after the
+    // delay, and its windows get their attempts back.
+    pub fn placed(&mut self, placed: &[(W, tile::Dimensions)], timers: &mut Timers<Timer>) {
+        if self.seen == placed {
+            return;
+        }
+        self.seen = placed.to_vec();
+        self.attempts.clear();
+        self.check_later(timers);
+    }
+
+    // A window moved by itself. Outside of enforce mode that's only looked at while the
+    // placement is still being checked anyway.
+    pub fn relocated(&mut self, window: W, timers: &mut Timers<Timer>) {
+        let managed = self.seen.iter().any(|(placed, _)| *placed == window);
+        if self.enforce && managed && !self.refusing.contains(&window) {
+            self.check_later(timers);
+        }
+    }
+
+    // a check that's already coming isn't put off by another one
+    pub fn check_later(&mut self, timers: &mut Timers<Timer>) {
+        if !timers.is_scheduled(Timer::Reconcile) {
+            timers.once(Timer::Reconcile, DELAY);
+        }
+    }
+
+    // Counts another attempt at putting the window back. False once it has had all of them, when
+    // it's flagged as refusing instead.
+    pub fn retry(&mut self, window: W) -> bool {
+        let attempts = match self.attempts.iter_mut().find(|(w, _)| *w == window) {
+            Some((_, attempts)) => attempts,
+            None => {
+                self.attempts.push((window, 0));
+                &mut self.attempts.last_mut().unwrap().1
+            }
+        };
+        if *attempts < ATTEMPTS {
+            *attempts += 1;
+            return true;
+        }
+        if !self.refusing.contains(&window) {
+            self.refusing.push(window);
+        }
+        false
+    }
+
+    pub fn forget(&mut self, window: W) {
+        self.refusing.retain(|w| *w != window);
+        self.attempts.retain(|(w, _)| *w != window);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+
+    fn dim(x: i32) -> tile::Dimensions {
+        tile::Dimensions { x: (x, 100), y: (0, 100) }
+    }
+
+    fn timers() -> (Timers<Timer>, FakeClock) {
+        let clock = FakeClock::default();
+        (Timers::new(Box::new(clock.clone())), clock)
+    }
+
+    #[test]
+    fn placed_should_schedule_check_only_for_new_placement() {
+        let (mut timers, clock) = timers();
+        let mut reconcile = Reconcile::new(false);
+
+        reconcile.placed(&[(1, dim(0))], &mut timers);
+        clock.advance(DELAY / 2);
+        assert!(timers.take_due().is_empty());
+        clock.advance(DELAY / 2);
+        assert_eq!(vec![Timer::Reconcile], timers.take_due());
+
+        reconcile.placed(&[(1, dim(0))], &mut timers);
+        assert!(!timers.is_scheduled(Timer::Reconcile));
+
+        reconcile.placed(&[(1, dim(50))], &mut timers);
+        assert!(timers.is_scheduled(Timer::Reconcile));
+    }
+
+    #[test]
+    fn retry_should_flag_window_once_attempts_run_out() {
+        let (mut timers, _clock) = timers();
+        let mut reconcile = Reconcile::new(false);
+        reconcile.placed(&[(1, dim(0)), (2, dim(100))], &mut timers);
+
+        for _ in 0..ATTEMPTS {
+            assert!(reconcile.retry(1));
+        }
+        assert!(reconcile.retry(2));
+        assert!(!reconcile.retry(1));
+        assert_eq!(vec![1], reconcile.refusing);
+
+        reconcile.forget(1);
+        assert!(reconcile.refusing.is_empty());
+        assert!(reconcile.retry(1));
+    }
+
+    #[test]
+    fn relocated_should_only_check_managed_windows_when_enforcing() {
+        let placed = [(1, dim(0))];
+        let (mut timers, clock) = timers();
+        let mut lenient = Reconcile::new(false);
+        let mut enforcing = Reconcile::new(true);
+        lenient.placed(&placed, &mut timers);
+        enforcing.placed(&placed, &mut timers);
+        clock.advance(DELAY);
+        timers.take_due();
+
+        lenient.relocated(1, &mut timers);
+        enforcing.relocated(2, &mut timers);
+        assert!(!timers.is_scheduled(Timer::Reconcile));
+
+        enforcing.relocated(1, &mut timers);
+        assert!(timers.is_scheduled(Timer::Reconcile));
+    }
+}
@@ -0,0 +1,379 @@
+use crate::config::Config;
+use crate::internal::{Backend, Handle, WindowEvent, WindowInfo};
+use crate::layout::{self, Layout};
+use crate::monitor::Monitor;
+use crate::tile;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+// A trace is a plain text file with one tab separated entry per line:
+//
+//   <ms since start> <kind> <fields...>
+//
+// It starts with the state the window manager was launched with (the config, screen dimensions,
+// existing windows, the active window and the saved session if there was one) followed by every
+// event in the order it was received. A window's size hints are written whenever they're read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEntry {
+    // a line of the config file
+    Config(String),
+    Dimensions(tile::Dimensions),
+    Monitor(Monitor),
+    Window(usize, WindowInfo),
+    Hints(usize, tile::SizeHints),
+    Active(usize),
+    Session(Layout),
+    Event(WindowEvent<usize>, WindowInfo),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceLine {
+    pub time: u128,
+    pub entry: TraceEntry,
+}
+
+pub struct Recorder {
+    out: Box<dyn Write>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        let file = File::create(path)?;
+        Ok(Recorder::new(Box::new(BufWriter::new(file))))
+    }
+
+    pub fn new(out: Box<dyn Write>) -> Recorder {
+        Recorder {
+            out,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn record_start<B: Backend>(&mut self, backend: &B, config: &Config, saved: Option<&Layout>) -> io::Result<()> {
+        for setting in config.settings() {
+            self.write(TraceEntry::Config(setting))?;
+        }
+        self.write(TraceEntry::Dimensions(backend.get_window_dimensions()))?;
+        for monitor in backend.get_monitors() {
+            self.write(TraceEntry::Monitor(monitor))?;
+        }
+        for window in backend.get_initial_windows() {
+            self.write(TraceEntry::Window(window.id(), backend.get_window_info(window)))?;
+            self.write(TraceEntry::Hints(window.id(), backend.get_size_hints(window)))?;
+        }
+        if let Some(window) = backend.get_active_window() {
+            self.write(TraceEntry::Active(window.id()))?;
+        }
+        if let Some(layout) = saved {
+            self.write(TraceEntry::Session(layout.clone()))?;
+        }
+        Ok(())
+    }
+
+    pub fn record_event<B: Backend>(&mut self, backend: &B, event: &WindowEvent<B::Window>) -> io::Result<()> {
+        let info = match event.window() {
+            Some(window) => backend.get_window_info(window),
+            None => WindowInfo::default(),
+        };
+        if let WindowEvent::Created(window) | WindowEvent::Relocated(window) = event {
+            self.write(TraceEntry::Hints(window.id(), backend.get_size_hints(*window)))?;
+        }
+        self.write(TraceEntry::Event(to_ids(event), info))
+    }
+
+    fn write(&mut self, entry: TraceEntry) -> io::Result<()> {
+        let line = TraceLine {
+            time: self.start.elapsed().as_millis(),
+            entry,
+        };
+        writeln!(self.out, "{}", format_line(&line))?;
+        // flush every line so a trace is still usable after a crash
+        self.out.flush()
+    }
+}
+
+fn to_ids<W: Handle>(event: &WindowEvent<W>) -> WindowEvent<usize> {
+    match event {
+        WindowEvent::Created(window) => WindowEvent::Created(window.id()),
+        WindowEvent::Destroyed(window) => WindowEvent::Destroyed(window.id()),
+        WindowEvent::FocusChanged(window) => WindowEvent::FocusChanged(window.id()),
+        WindowEvent::Hidden(window) => WindowEvent::Hidden(window.id()),
+        WindowEvent::Shown(window) => WindowEvent::Shown(window.id()),
+        WindowEvent::Moved(window, dim, point) => WindowEvent::Moved(window.id(), dim.clone(), *point),
+        WindowEvent::Relocated(window) => WindowEvent::Relocated(window.id()),
+        WindowEvent::MonitorsChanged(monitors) => WindowEvent::MonitorsChanged(monitors.clone()),
+        WindowEvent::MouseMoved(x, y) => WindowEvent::MouseMoved(*x, *y),
+        WindowEvent::Command(command) => WindowEvent::Command(command.clone()),
+    }
+}
+
+pub fn format_line(line: &TraceLine) -> String {
+    let fields = match &line.entry {
+        TraceEntry::Config(setting) => format!("config\t{}", clean(setting)),
+        TraceEntry::Dimensions(dim) => {
+            format!("dimensions\t{}\t{}\t{}\t{}", dim.x.0, dim.x.1, dim.y.0, dim.y.1)
+        }
+        TraceEntry::Monitor(monitor) => format!("monitor\t{}", format_monitor(monitor, "\t")),
+        TraceEntry::Window(id, info) => format!("window\t{}\t{}", id, format_info(info)),
+        TraceEntry::Hints(id, hints) => format!(
+            "hints\t{}\t{}\t{}\t{}\t{}",
+            id, hints.min.0, hints.min.1, hints.max.0, hints.max.1
+        ),
+        TraceEntry::Active(id) => format!("active\t{}", id),
+        // compact JSON never contains raw tabs or newlines, so it fits on one line
+        TraceEntry::Session(layout) => format!(
+            "session\t{}",
+            serde_json::to_string(layout).expect("layout should always be serializable")
+        ),
+        TraceEntry::Event(WindowEvent::Created(id), info) => {
+            format!("created\t{}\t{}", id, format_info(info))
+        }
+        TraceEntry::Event(WindowEvent::Destroyed(id), info) => {
+            format!("destroyed\t{}\t{}", id, format_info(info))
+        }
+        TraceEntry::Event(WindowEvent::FocusChanged(id), info) => {
+            format!("focus\t{}\t{}", id, format_info(info))
+        }
+        TraceEntry::Event(WindowEvent::Hidden(id), info) => format!("hidden\t{}\t{}", id, format_info(info)),
+        TraceEntry::Event(WindowEvent::Shown(id), info) => format!("shown\t{}\t{}", id, format_info(info)),
+        TraceEntry::Event(WindowEvent::Relocated(id), info) => {
+            format!("relocated\t{}\t{}", id, format_info(info))
+        }
+        TraceEntry::Event(WindowEvent::Moved(id, dim, (x, y)), _) => format!(
+            "moved\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            id, dim.x.0, dim.x.1, dim.y.0, dim.y.1, x, y
+        ),
+        // one field per monitor, with its values separated by commas
+        TraceEntry::Event(WindowEvent::MonitorsChanged(monitors), _) => {
+            let monitors: Vec<String> = monitors.iter().map(|monitor| format_monitor(monitor, ",")).collect();
+            format!("monitors\t{}", monitors.join("\t"))
+        }
+        TraceEntry::Event(WindowEvent::MouseMoved(x, y), _) => format!("mouse\t{}\t{}", x, y),
+        TraceEntry::Event(WindowEvent::Command(command), _) => format!("command\t{}", command),
+    };
+    format!("{}\t{}", line.time, fields)
+}
+
+fn format_monitor(monitor: &Monitor, separator: &str) -> String {
+    let (area, work) = (&monitor.area, &monitor.work_area);
+    let values = [area.x.0, area.x.1, area.y.0, area.y.1, work.x.0, work.x.1, work.y.0, work.y.1];
+    let mut fields = vec![clean(&monitor.id).replace(',', " ")];
+    fields.extend(values.iter().map(|value| value.to_string()));
+    fields.push(monitor.scale.to_string());
+    fields.join(separator)
+}
+
+fn format_info(info: &WindowInfo) -> String {
+    format!("{}\t{}\t{}", clean(&info.process), clean(&info.class), clean(&info.title))
+}
+
+// tabs and newlines would break the line format, and are never interesting in a title
+fn clean(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+pub fn parse(contents: &str) -> io::Result<Vec<TraceLine>> {
+    let mut lines = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = parse_line(line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("trace line {}: {}", number + 1, e))
+        })?;
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn parse_line(line: &str) -> Result<TraceLine, String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 2 {
+        return Err("expected a time and an entry kind".to_string());
+    }
+
+    let time = parse_number(fields[0])?;
+    let args = &fields[2..];
+    let entry = match fields[1] {
+        "config" => {
+            let setting = args.join("\t");
+            Config::parse(&setting)?;
+            TraceEntry::Config(setting)
+        }
+        "dimensions" => TraceEntry::Dimensions(parse_dimensions("dimensions", args)?),
+        "window" => TraceEntry::Window(parse_window(args)?, parse_info(args)),
+        "hints" => match args {
+            [id, min_width, min_height, max_width, max_height] => TraceEntry::Hints(
+                parse_number(id)?,
+                tile::SizeHints {
+                    min: (parse_number(min_width)?, parse_number(min_height)?),
+                    max: (parse_number(max_width)?, parse_number(max_height)?),
+                },
+            ),
+            _ => return Err("hints needs a window and 4 sizes".to_string()),
+        },
+        "active" => TraceEntry::Active(parse_window(args)?),
+        "session" => TraceEntry::Session(layout::from_json(&args.join("\t"))?),
+        "created" => TraceEntry::Event(WindowEvent::Created(parse_window(args)?), parse_info(args)),
+        "destroyed" => TraceEntry::Event(WindowEvent::Destroyed(parse_window(args)?), parse_info(args)),
+        "focus" => TraceEntry::Event(WindowEvent::FocusChanged(parse_window(args)?), parse_info(args)),
+        "hidden" => TraceEntry::Event(WindowEvent::Hidden(parse_window(args)?), parse_info(args)),
+        "shown" => TraceEntry::Event(WindowEvent::Shown(parse_window(args)?), parse_info(args)),
+        "relocated" => TraceEntry::Event(WindowEvent::Relocated(parse_window(args)?), parse_info(args)),
+        "moved" => {
+            if args.len() != 7 {
+                return Err("moved needs a window, 4 dimensions and a point".to_string());
+            }
+            let dim = parse_dimensions("moved", &args[1..5])?;
+            let point = (parse_number(args[5])?, parse_number(args[6])?);
+            TraceEntry::Event(WindowEvent::Moved(parse_window(args)?, dim, point), WindowInfo::default())
+        }
+        "monitor" => TraceEntry::Monitor(parse_monitor(args)?),
+        "monitors" => {
+            let monitors = args
+                .iter()
+                .map(|monitor| parse_monitor(&monitor.split(',').collect::<Vec<_>>()))
+                .collect::<Result<_, _>>()?;
+            TraceEntry::Event(WindowEvent::MonitorsChanged(monitors), WindowInfo::default())
+        }
+        "mouse" => match args {
+            [x, y] => TraceEntry::Event(WindowEvent::MouseMoved(parse_number(x)?, parse_number(y)?), WindowInfo::default()),
+            _ => return Err("mouse needs a point".to_string()),
+        },
+        "command" => TraceEntry::Event(WindowEvent::Command(args.join("\t").parse()?), WindowInfo::default()),
+        kind => return Err(format!("unknown entry kind '{}'", kind)),
+    };
+
+    Ok(TraceLine { time, entry })
+}
+
+fn parse_dimensions(kind: &str, args: &[&str]) -> Result<tile::Dimensions, String> {
+    if args.len() != 4 {
+        return Err(format!("{} needs 4 values", kind));
+    }
+    Ok(tile::Dimensions {
+        x: (parse_number(args[0])?, parse_number(args[1])?),
+        y: (parse_number(args[2])?, parse_number(args[3])?),
+    })
+}
+
+fn parse_monitor(args: &[&str]) -> Result<Monitor, String> {
+    if args.len() != 10 {
+        return Err("monitor needs an id, 8 dimensions and a scale".to_string());
+    }
+    Ok(Monitor {
+        id: args[0].to_string(),
+        area: parse_dimensions("monitor", &args[1..5])?,
+        work_area: parse_dimensions("monitor", &args[5..9])?,
+        scale: parse_number(args[9])?,
+    })
+}
+
+fn parse_number<N: std::str::FromStr>(value: &str) -> Result<N, String> {
+    value.trim().parse().map_err(|_| format!("'{}' is not a number", value))
+}
+
+fn parse_window(args: &[&str]) -> Result<usize, String> {
+    match args.first() {
+        Some(id) => parse_number(id),
+        None => Err("missing window id".to_string()),
+    }
+}
+
+fn parse_info(args: &[&str]) -> WindowInfo {
+    let field = |i: usize| args.get(i).map(|s| s.to_string()).unwrap_or_default();
+    WindowInfo {
+        process: field(1),
+        class: field(2),
+        title: field(3),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+    use crate::command::Command;
+    use crate::core::State;
+    use crate::workspace::Workspace;
+
+    fn session() -> Layout {
+        let mut workspace = Workspace::new("1", tile::Dimensions { x: (0, 1920), y: (0, 1080) });
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 10);
+        let state = State::new(vec![workspace], Box::new(FakeClock::default()));
+        layout::capture(&state, &|_| WindowInfo {
+            title: "tab\there".to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn monitor() -> Monitor {
+        Monitor {
+            id: "\\\\.\\DISPLAY1".to_string(),
+            area: tile::Dimensions { x: (-1920, 1920), y: (0, 1080) },
+            work_area: tile::Dimensions { x: (-1920, 1920), y: (0, 1040) },
+            scale: 1.25,
+        }
+    }
+
+    #[test]
+    fn format_and_parse_should_round_trip_every_entry_kind() {
+        let info = WindowInfo {
+            title: "notes.txt - Notepad".to_string(),
+            class: "Notepad".to_string(),
+            process: "notepad.exe".to_string(),
+        };
+        let lines = vec![
+            TraceLine { time: 0, entry: TraceEntry::Config("set gaps 8".to_string()) },
+            TraceLine { time: 0, entry: TraceEntry::Dimensions(tile::Dimensions { x: (0, 1920), y: (0, 1080) }) },
+            TraceLine { time: 0, entry: TraceEntry::Window(10, info.clone()) },
+            TraceLine { time: 0, entry: TraceEntry::Hints(10, tile::SizeHints { min: (300, 200), max: (i32::MAX, 900) }) },
+            TraceLine { time: 0, entry: TraceEntry::Monitor(monitor()) },
+            TraceLine { time: 0, entry: TraceEntry::Active(10) },
+            TraceLine { time: 0, entry: TraceEntry::Session(session()) },
+            TraceLine { time: 5, entry: TraceEntry::Event(WindowEvent::Created(20), info.clone()) },
+            TraceLine { time: 6, entry: TraceEntry::Event(WindowEvent::FocusChanged(20), info.clone()) },
+            TraceLine { time: 6, entry: TraceEntry::Event(WindowEvent::Hidden(20), info.clone()) },
+            TraceLine { time: 6, entry: TraceEntry::Event(WindowEvent::Shown(20), info.clone()) },
+            TraceLine { time: 7, entry: TraceEntry::Event(WindowEvent::Command(Command::ToggleOrientation), WindowInfo::default()) },
+            TraceLine { time: 8, entry: TraceEntry::Event(WindowEvent::Moved(20, tile::Dimensions { x: (5, 900), y: (0, 1080) }, (450, 10)), WindowInfo::default()) },
+            TraceLine { time: 8, entry: TraceEntry::Event(WindowEvent::Relocated(20), info.clone()) },
+            TraceLine { time: 8, entry: TraceEntry::Event(WindowEvent::MonitorsChanged(vec![monitor(), monitor()]), WindowInfo::default()) },
+            TraceLine { time: 8, entry: TraceEntry::Event(WindowEvent::MouseMoved(300, -20), WindowInfo::default()) },
+            TraceLine { time: 9, entry: TraceEntry::Event(WindowEvent::Destroyed(20), info) },
+        ];
+
+        let contents: Vec<String> = lines.iter().map(format_line).collect();
+        let parsed = parse(&contents.join("\n")).unwrap();
+
+        assert_eq!(lines, parsed);
+    }
+
+    #[test]
+    fn format_line_should_strip_tabs_from_titles() {
+        let info = WindowInfo {
+            title: "a\tb".to_string(),
+            ..Default::default()
+        };
+        let line = TraceLine { time: 1, entry: TraceEntry::Event(WindowEvent::Created(3), info) };
+
+        let parsed = parse(&format_line(&line)).unwrap();
+
+        if let TraceEntry::Event(_, info) = &parsed[0].entry {
+            assert_eq!("a b", info.title);
+        } else {
+            panic!("Parsed entry is not an event");
+        }
+    }
+
+    #[test]
+    fn parse_should_report_line_of_invalid_entry() {
+        let result = parse("0\tactive\t1\n1\tteleported\t2");
+
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("trace line 2"));
+    }
+}
@@ -0,0 +1,252 @@
+use super::clock::FakeClock;
+use super::record::{self, TraceEntry, TraceLine};
+use super::State;
+use crate::internal::mock::{self, MockBackend};
+use crate::internal::{Backend, WindowEvent};
+use crate::config::Config;
+use crate::layout;
+use crate::tile;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// Replays the trace in the file and gives back the resulting tree followed by every window
+// position call, a line each.
+pub fn run(path: &Path) -> io::Result<Vec<String>> {
+    let trace = record::parse(&fs::read_to_string(path)?)?;
+    let (state, backend) = replay(&trace, FakeClock::default());
+
+    let tree = layout::capture(&state, &|window| backend.get_window_info(window));
+    let mut lines = vec![layout::to_json(&tree)];
+    lines.extend(backend.calls.iter().map(|call| call.to_string()));
+    Ok(lines)
+}
+
+// Feeds a recorded trace through the same code paths as a live session, against a mock
+// backend. Events are handled in the order recorded, and the clock follows the recorded times so
+// anything waiting on a timer happens between the same events it did live.
+pub fn replay(trace: &[TraceLine], clock: FakeClock) -> (State<usize>, MockBackend) {
+    let mut backend = MockBackend::new(tile::Dimensions { x: (0, 0), y: (0, 0) });
+    let mut saved = None;
+    let mut monitors = Vec::new();
+    let mut settings = Vec::new();
+
+    // everything before the first event is what the window manager started with
+    let first_event = trace
+        .iter()
+        .position(|line| matches!(line.entry, TraceEntry::Event(..)))
+        .unwrap_or(trace.len());
+    let (header, events) = trace.split_at(first_event);
+    for line in header {
+        match &line.entry {
+            TraceEntry::Config(setting) => settings.push(setting.as_str()),
+            TraceEntry::Dimensions(dim) => {
+                backend.dimensions = dim.clone();
+                backend.monitor = dim.clone();
+                backend.monitors = vec![mock::primary(dim)];
+            }
+            TraceEntry::Monitor(monitor) => monitors.push(monitor.clone()),
+            TraceEntry::Window(id, info) => {
+                backend.windows.push(*id);
+                backend.info.insert(*id, info.clone());
+            }
+            TraceEntry::Hints(id, hints) => {
+                backend.hints.insert(*id, *hints);
+            }
+            TraceEntry::Active(id) => backend.active = Some(*id),
+            TraceEntry::Session(layout) => saved = Some(layout.clone()),
+            TraceEntry::Event(..) => (),
+        }
+    }
+
+    // traces from before monitors were recorded only have the primary's dimensions
+    if !monitors.is_empty() {
+        backend.monitors = monitors;
+    }
+    // and the ones from before the config was recorded were made with the defaults
+    let config = Config::parse(&settings.join("\n")).expect("config lines are checked when parsing the trace");
+    let mut state = super::start(&mut backend, saved.as_ref(), &config, Box::new(clock.clone()));
+
+    let mut now = header.last().map_or(0, |line| line.time);
+    for line in events {
+        // whatever came due before this line happens first
+        clock.advance(Duration::from_millis(line.time.saturating_sub(now) as u64));
+        now = now.max(line.time);
+        super::run_timers(&mut backend, &mut state, None);
+
+        match &line.entry {
+            TraceEntry::Hints(id, hints) => {
+                backend.hints.insert(*id, *hints);
+            }
+            TraceEntry::Event(event, info) => {
+                if let Some(id) = event.window() {
+                    // handles get reused, what was recorded with the event is what it was then
+                    backend.info.insert(id, info.clone());
+                }
+                if let WindowEvent::MouseMoved(x, y) = event {
+                    backend.cursor = Some((*x, *y));
+                }
+                super::handle_event(&mut backend, &mut state, event.clone());
+            }
+            _ => (),
+        }
+    }
+
+    (state, backend)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::mock::Call;
+
+    const TRACE: &str = "\
+0\tdimensions\t0\t1920\t0\t1080
+0\twindow\t1\tcode.exe\tChrome_WidgetWin_1\tmain.rs
+0\tactive\t1
+10\tcreated\t2\tcmd.exe\tConsoleWindowClass\tC:\\Windows\\system32\\cmd.exe
+11\tfocus\t2\tcmd.exe\tConsoleWindowClass\tC:\\Windows\\system32\\cmd.exe
+20\tcommand\torientation toggle
+30\tcreated\t3\tnotepad.exe\tNotepad\tUntitled - Notepad
+40\tdestroyed\t1\tcode.exe\tChrome_WidgetWin_1\tmain.rs
+";
+
+    #[test]
+    fn replay_should_split_focused_window_with_current_orientation() {
+        let trace = record::parse(TRACE).unwrap();
+
+        let (state, _) = replay(&trace, FakeClock::default());
+
+        // window 1 is gone, so the split holding 2 and 3 takes over the whole screen
+        let root = &state.workspace().root;
+        if let tile::NodeType::Separator(tile::Orientation::Vertical, _, left, right) = &root.node_type {
+            assert!(matches!(left.node_type, tile::NodeType::Window(2)));
+            assert!(matches!(right.node_type, tile::NodeType::Window(3)));
+            assert_eq!(tile::Dimensions { x: (0, 960), y: (0, 1080) }, left.dim);
+            assert_eq!(tile::Dimensions { x: (961, 960), y: (0, 1080) }, right.dim);
+        } else {
+            panic!("Root is not a vertical separator");
+        }
+    }
+
+    #[test]
+    fn replay_should_record_every_position_call() {
+        let trace = record::parse(TRACE).unwrap();
+
+        let (_, backend) = replay(&trace, FakeClock::default());
+
+        assert_eq!(
+            vec![
+                Call::Show(1),
+                Call::SetPos(1, 0, 0, 1920, 1080),
+                Call::Show(1),
+                Call::Show(2),
+                Call::SetPos(1, 0, 0, 1920, 540),
+                Call::SetPos(2, 0, 541, 1920, 540),
+                // 1 stays where it is when 2 is split
+                Call::Show(2),
+                Call::Show(3),
+                Call::SetPos(2, 0, 541, 960, 540),
+                Call::SetPos(3, 961, 541, 960, 540),
+                Call::Show(2),
+                Call::Show(3),
+                Call::SetPos(2, 0, 0, 960, 1080),
+                Call::SetPos(3, 961, 0, 960, 1080),
+            ],
+            backend.calls
+        );
+    }
+
+    #[test]
+    fn replay_should_adopt_windows_from_saved_session() {
+        let mut saved = crate::workspace::Workspace::new("1", tile::Dimensions { x: (0, 1920), y: (0, 1080) });
+        tile::tile(&mut saved.root, tile::Orientation::Vertical, 1);
+        tile::tile(&mut saved.root, tile::Orientation::Vertical, 2);
+        let saved = State::new(vec![saved], Box::new(FakeClock::default()));
+        let session = layout::capture(&saved, &|window| crate::internal::WindowInfo {
+            title: format!("window {}", window),
+            class: "Class".to_string(),
+            process: "app.exe".to_string(),
+        });
+        let mut trace = record::parse("\
+0\tdimensions\t0\t1920\t0\t1080
+0\twindow\t5\tapp.exe\tClass\twindow 2
+0\twindow\t6\tnotepad.exe\tNotepad\tUntitled - Notepad
+").unwrap();
+        trace.push(TraceLine { time: 0, entry: TraceEntry::Session(session) });
+
+        let (state, _) = replay(&trace, FakeClock::default());
+
+        // window 1 didn't come back, 5 took the place of 2 and 6 was tiled next to it
+        let root = &state.workspace().root;
+        if let tile::NodeType::Separator(tile::Orientation::Horizontal, _, top, bottom) = &root.node_type {
+            assert_eq!(tile::NodeType::Window(5), top.node_type);
+            assert_eq!(tile::NodeType::Window(6), bottom.node_type);
+        } else {
+            panic!("Root is not a horizontal separator");
+        }
+    }
+
+    #[test]
+    fn replay_should_lay_out_with_recorded_config_and_size_hints() {
+        let trace = record::parse("\
+0\tconfig\tset gaps 10
+0\tdimensions\t0\t1920\t0\t1080
+0\twindow\t1
+0\tactive\t1
+10\tcommand\torientation toggle
+20\thints\t2\t1200\t0\t2147483647\t2147483647
+20\tcreated\t2
+").unwrap();
+
+        let (_, backend) = replay(&trace, FakeClock::default());
+
+        // gaps all around, and 1 gives up most of its space for 2's minimum width
+        assert_eq!(
+            &[Call::SetPos(1, 10, 10, 695, 1060), Call::SetPos(2, 716, 10, 1195, 1060)],
+            &backend.calls[backend.calls.len() - 2..]
+        );
+    }
+
+    #[test]
+    fn replay_should_run_timers_between_events_at_recorded_times() {
+        let trace = "\
+0\tconfig\tset focus_follows_mouse 100
+0\tdimensions\t0\t1920\t0\t1080
+0\twindow\t2
+0\tactive\t2
+10\tcommand\torientation toggle
+20\tcreated\t3
+30\tmouse\t1500\t500
+";
+
+        // the cursor rested on 3 long enough before the next event came in
+        let (state, backend) = replay(&record::parse(&format!("{}200\tmouse\t1500\t501\n", trace)).unwrap(), FakeClock::default());
+        assert_eq!(Some(3), state.workspace().focus);
+        assert!(backend.calls.contains(&Call::Focus(3)));
+
+        let (state, _) = replay(&record::parse(&format!("{}80\tmouse\t1500\t501\n", trace)).unwrap(), FakeClock::default());
+        assert_eq!(Some(2), state.workspace().focus);
+    }
+
+    #[test]
+    fn replay_should_use_info_of_window_that_reused_a_handle() {
+        let trace = record::parse("\
+0\tconfig\tscratchpad notes class=Notepad notepad.exe
+0\tdimensions\t0\t1920\t0\t1080
+0\twindow\t1
+0\tactive\t1
+10\tcreated\t2\tcmd.exe\tConsoleWindowClass\tcmd
+20\tdestroyed\t2
+30\tcommand\tscratchpad show notes
+40\tcreated\t2\tnotepad.exe\tNotepad\tUntitled - Notepad
+").unwrap();
+
+        let (state, _) = replay(&trace, FakeClock::default());
+
+        // the second 2 is the notepad the scratchpad launched, not the console that had it before
+        assert_eq!(vec![1], tile::windows(&state.workspace().root));
+        assert_eq!(2, state.scratchpad.windows[0].window);
+    }
+}
@@ -0,0 +1,117 @@
+use super::clock::SystemClock;
+use super::State;
+use crate::internal::{Backend, Handle, WindowInfo};
+use crate::layout::{self, Layout};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Bursts of events (opening an app usually creates and focuses a couple of windows) should
+// only write the session once.
+pub const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+pub struct Session {
+    path: PathBuf,
+    // the layout when it was last looked at, to only save it again once it changes
+    last: Option<Layout>,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("tiles10").join("session.json"))
+}
+
+impl Session {
+    pub fn new(path: PathBuf) -> Session {
+        Session { path, last: None }
+    }
+
+    // None when nothing was saved yet, an error when what was saved can't be used.
+    pub fn load(&self) -> Result<Option<Layout>, String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+        let layout = layout::from_json(&contents).and_then(|layout| {
+            layout.to_state::<usize>(Box::new(SystemClock))?;
+            Ok(layout)
+        });
+        layout
+            .map(Some)
+            .map_err(|e| format!("ignoring saved session {}: {}", self.path.display(), e))
+    }
+
+    pub fn save<B: Backend>(&self, state: &State<B::Window>, backend: &B) -> Result<(), String> {
+        let tree = layout::capture(state, &|window| backend.get_window_info(window));
+        self.write(&layout::to_json(&tree))
+            .map_err(|e| format!("could not save session to {}: {}", self.path.display(), e))
+    }
+
+    // Whether the layout is different from the last time this was asked. Focus moving or the
+    // mouse resting somewhere often leaves it as it was, and there's no point writing it again.
+    pub fn changed<W: Handle>(&mut self, state: &State<W>) -> bool {
+        // window info only matters for what's written, and asking every window for it is slow
+        let layout = layout::capture(state, &|_| WindowInfo::default());
+        if self.last.as_ref() == Some(&layout) {
+            return false;
+        }
+        self.last = Some(layout);
+        true
+    }
+
+    // write to a temporary file first so a crash mid-write can't leave a truncated session
+    fn write(&self, contents: &str) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+
+    use crate::internal::mock::MockBackend;
+    use crate::tile;
+    use crate::workspace::Workspace;
+
+    #[test]
+    fn save_should_write_layout_that_loads_again() {
+        let dir = std::env::temp_dir().join(format!("tiles10-session-{}", std::process::id()));
+        let session = Session::new(dir.join("session.json"));
+        let dim = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+        let mut workspace = Workspace::new("1", dim.clone());
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 1);
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 2);
+
+        session.save(&State::new(vec![workspace], Box::new(FakeClock::default())), &MockBackend::new(dim)).unwrap();
+        let loaded = session.load();
+        fs::write(dir.join("session.json"), "{").unwrap();
+        let broken = session.load();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let state = loaded.unwrap().unwrap().to_state::<usize>(Box::new(FakeClock::default())).unwrap();
+        assert_eq!(vec![1, 2], tile::windows(&state.workspace().root));
+        assert!(broken.is_err());
+        assert_eq!(Ok(None), session.load());
+    }
+
+    #[test]
+    fn changed_should_only_be_true_once_layout_changed() {
+        let mut session = Session::new(PathBuf::from("session.json"));
+        let dim = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+        let mut workspace = Workspace::new("1", dim);
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 1);
+        let mut state = State::new(vec![workspace], Box::new(FakeClock::default()));
+
+        assert!(session.changed(&state));
+        assert!(!session.changed(&state));
+        tile::tile(&mut state.workspace_mut().root, tile::Orientation::Vertical, 2);
+        assert!(session.changed(&state));
+        assert!(!session.changed(&state));
+    }
+}
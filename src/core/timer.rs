@@ -0,0 +1,165 @@
+use super::clock::Clock;
+use std::time::{Duration, Instant};
+
+// Things that happen a while after something else did. Each key has at most one timer, so
+// scheduling it again moves it instead of adding another.
+pub struct Timers<K> {
+    clock: Box<dyn Clock>,
+    timers: Vec<Timer<K>>,
+}
+
+struct Timer<K> {
+    key: K,
+    due: Instant,
+    // repeating timers are scheduled again this long after they fire
+    every: Option<Duration>,
+}
+
+impl<K: Copy + PartialEq> Timers<K> {
+    pub fn new(clock: Box<dyn Clock>) -> Timers<K> {
+        Timers { clock, timers: Vec::new() }
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    // Fires key once, after the delay.
+    pub fn once(&mut self, key: K, after: Duration) {
+        self.schedule(key, after, None);
+    }
+
+    // Fires key every interval, starting one interval from now.
+    pub fn repeat(&mut self, key: K, every: Duration) {
+        self.schedule(key, every, Some(every));
+    }
+
+    pub fn cancel(&mut self, key: K) {
+        self.timers.retain(|timer| timer.key != key);
+    }
+
+    pub fn is_scheduled(&self, key: K) -> bool {
+        self.timers.iter().any(|timer| timer.key == key)
+    }
+
+    // How long until the next timer fires, zero if one already should have. None when nothing is
+    // scheduled, so there's nothing to wake up for.
+    pub fn next_due(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.timers.iter().map(|timer| timer.due.saturating_duration_since(now)).min()
+    }
+
+    // The keys whose time has come, the one that was due first first. Repeating timers stay
+    // scheduled, the rest are done.
+    pub fn take_due(&mut self) -> Vec<K> {
+        let now = self.clock.now();
+        let mut due: Vec<(Instant, K)> = self
+            .timers
+            .iter()
+            .filter(|timer| timer.due <= now)
+            .map(|timer| (timer.due, timer.key))
+            .collect();
+        due.sort_by_key(|(at, _)| *at);
+
+        self.timers.retain(|timer| timer.due > now || timer.every.is_some());
+        for timer in &mut self.timers {
+            if let Some(every) = timer.every.filter(|_| timer.due <= now) {
+                // a repeating timer that fell behind fires once, not once for every missed tick
+                while timer.due <= now {
+                    timer.due += every.max(Duration::from_millis(1));
+                }
+            }
+        }
+        due.into_iter().map(|(_, key)| key).collect()
+    }
+
+    fn schedule(&mut self, key: K, after: Duration, every: Option<Duration>) {
+        self.cancel(key);
+        let due = self.clock.now() + after;
+        self.timers.push(Timer { key, due, every });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+
+    fn timers() -> (Timers<&'static str>, FakeClock) {
+        let clock = FakeClock::default();
+        (Timers::new(Box::new(clock.clone())), clock)
+    }
+
+    #[test]
+    fn once_should_fire_a_single_time_after_delay() {
+        let (mut timers, clock) = timers();
+        timers.once("save", Duration::from_millis(500));
+
+        clock.advance(Duration::from_millis(499));
+        assert!(timers.take_due().is_empty());
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(vec!["save"], timers.take_due());
+        clock.advance(Duration::from_secs(10));
+        assert!(timers.take_due().is_empty());
+        assert!(!timers.is_scheduled("save"));
+    }
+
+    #[test]
+    fn once_should_move_timer_that_is_already_scheduled() {
+        let (mut timers, clock) = timers();
+        timers.once("save", Duration::from_millis(500));
+
+        clock.advance(Duration::from_millis(400));
+        timers.once("save", Duration::from_millis(500));
+        clock.advance(Duration::from_millis(400));
+        assert!(timers.take_due().is_empty());
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(vec!["save"], timers.take_due());
+    }
+
+    #[test]
+    fn repeat_should_keep_firing_until_cancelled() {
+        let (mut timers, clock) = timers();
+        timers.repeat("poll", Duration::from_millis(250));
+
+        for _ in 0..3 {
+            clock.advance(Duration::from_millis(250));
+            assert_eq!(vec!["poll"], timers.take_due());
+        }
+        // behind by several ticks, it catches up with a single one
+        clock.advance(Duration::from_millis(1000));
+        assert_eq!(vec!["poll"], timers.take_due());
+        clock.advance(Duration::from_millis(100));
+        assert!(timers.take_due().is_empty());
+
+        timers.cancel("poll");
+        clock.advance(Duration::from_secs(1));
+        assert!(timers.take_due().is_empty());
+    }
+
+    #[test]
+    fn next_due_should_count_down_to_earliest_timer() {
+        let (mut timers, clock) = timers();
+        assert_eq!(None, timers.next_due());
+
+        timers.once("late", Duration::from_millis(300));
+        timers.repeat("poll", Duration::from_millis(250));
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(Some(Duration::from_millis(150)), timers.next_due());
+
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(Some(Duration::ZERO), timers.next_due());
+    }
+
+    #[test]
+    fn take_due_should_fire_timers_in_order_they_were_due() {
+        let (mut timers, clock) = timers();
+        timers.once("late", Duration::from_millis(300));
+        timers.once("early", Duration::from_millis(100));
+        timers.once("later", Duration::from_millis(900));
+
+        clock.advance(Duration::from_millis(300));
+
+        assert_eq!(vec!["early", "late"], timers.take_due());
+    }
+}
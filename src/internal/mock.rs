@@ -0,0 +1,175 @@
+use super::{Backend, WindowEvent, WindowInfo};
+use crate::config::Binding;
+use crate::monitor::Monitor;
+use crate::tile;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+// In-memory backend used for replaying traces and for tests. Windows are plain integers and
+// every call that would touch a real window is recorded instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    Show(usize),
+    Hide(usize),
+    Focus(usize),
+    MoveCursor(i32, i32),
+    SetPos(usize, i32, i32, i32, i32),
+    Launch(String),
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Call::Show(window) => write!(f, "show {}", window),
+            Call::Hide(window) => write!(f, "hide {}", window),
+            Call::Focus(window) => write!(f, "focus {}", window),
+            Call::MoveCursor(x, y) => write!(f, "move_cursor x={} y={}", x, y),
+            Call::SetPos(window, x, y, cx, cy) => {
+                write!(f, "set_pos {} x={} y={} cx={} cy={}", window, x, y, cx, cy)
+            }
+            Call::Launch(command_line) => write!(f, "launch {}", command_line),
+        }
+    }
+}
+
+pub struct MockBackend {
+    pub dimensions: tile::Dimensions,
+    pub monitor: tile::Dimensions,
+    pub monitors: Vec<Monitor>,
+    pub windows: Vec<usize>,
+    pub active: Option<usize>,
+    pub info: HashMap<usize, WindowInfo>,
+    pub hints: HashMap<usize, tile::SizeHints>,
+    // where each window is, set_window_pos puts them there unless they're stuck
+    pub rects: HashMap<usize, tile::Dimensions>,
+    pub stuck: Vec<usize>,
+    // where the cursor is, None until something puts it somewhere
+    pub cursor: Option<(i32, i32)>,
+    // programs that fail to start
+    pub missing: Vec<String>,
+    pub events: VecDeque<WindowEvent<usize>>,
+    pub calls: Vec<Call>,
+}
+
+impl MockBackend {
+    pub fn new(dimensions: tile::Dimensions) -> MockBackend {
+        MockBackend {
+            monitor: dimensions.clone(),
+            monitors: vec![primary(&dimensions)],
+            dimensions,
+            windows: Vec::new(),
+            active: None,
+            info: HashMap::new(),
+            hints: HashMap::new(),
+            rects: HashMap::new(),
+            stuck: Vec::new(),
+            cursor: None,
+            missing: Vec::new(),
+            events: VecDeque::new(),
+            calls: Vec::new(),
+        }
+    }
+}
+
+// a single monitor covering the given dimensions
+pub fn primary(dimensions: &tile::Dimensions) -> Monitor {
+    Monitor {
+        id: "1".to_string(),
+        area: dimensions.clone(),
+        work_area: dimensions.clone(),
+        scale: 1.0,
+    }
+}
+
+impl Backend for MockBackend {
+    type Window = usize;
+
+    fn create_hooks(&mut self, _bindings: &[Binding]) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn watch_mouse(&mut self) {}
+
+    fn next_event(&mut self) -> Option<WindowEvent<usize>> {
+        self.events.pop_front()
+    }
+
+    // every event is queued up front, there's nothing to wait for
+    fn wait(&mut self, _timeout: Option<Duration>) {}
+
+    fn get_active_window(&self) -> Option<usize> {
+        self.active
+    }
+
+    fn get_initial_windows(&self) -> Vec<usize> {
+        self.windows.clone()
+    }
+
+    fn get_window_dimensions(&self) -> tile::Dimensions {
+        self.dimensions.clone()
+    }
+
+    fn get_monitor_dimensions(&self) -> tile::Dimensions {
+        self.monitor.clone()
+    }
+
+    fn get_monitors(&self) -> Vec<Monitor> {
+        self.monitors.clone()
+    }
+
+    fn get_window_info(&self, window: usize) -> WindowInfo {
+        self.info.get(&window).cloned().unwrap_or_default()
+    }
+
+    fn get_size_hints(&self, window: usize) -> tile::SizeHints {
+        self.hints.get(&window).copied().unwrap_or_default()
+    }
+
+    fn get_window_rect(&self, window: usize) -> Option<tile::Dimensions> {
+        self.rects.get(&window).cloned()
+    }
+
+    fn show_window(&mut self, window: usize) {
+        self.calls.push(Call::Show(window));
+    }
+
+    fn hide_window(&mut self, window: usize) {
+        self.calls.push(Call::Hide(window));
+    }
+
+    fn focus_window(&mut self, window: usize) {
+        self.calls.push(Call::Focus(window));
+    }
+
+    fn get_cursor_pos(&self) -> Option<(i32, i32)> {
+        self.cursor
+    }
+
+    fn move_cursor(&mut self, x: i32, y: i32) {
+        self.calls.push(Call::MoveCursor(x, y));
+        self.cursor = Some((x, y));
+    }
+
+    fn launch(&mut self, command_line: &str) -> Result<(), String> {
+        self.calls.push(Call::Launch(command_line.to_string()));
+        if self.missing.iter().any(|missing| missing == command_line) {
+            return Err(format!("could not launch '{}'", command_line));
+        }
+        Ok(())
+    }
+
+    fn set_window_pos(&mut self, window: usize, x: i32, y: i32, cx: i32, cy: i32) -> bool {
+        self.calls.push(Call::SetPos(window, x, y, cx, cy));
+        if !self.stuck.contains(&window) {
+            self.rects.insert(window, tile::Dimensions { x: (x, cx), y: (y, cy) });
+        }
+        true
+    }
+
+    fn set_window_positions(&mut self, positions: &[(usize, tile::Dimensions)]) {
+        for (window, dim) in positions {
+            self.set_window_pos(*window, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+        }
+    }
+}
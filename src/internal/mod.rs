@@ -1,3 +1,141 @@
+#[cfg(all(windows, feature = "windows"))]
 pub mod win;
+pub mod mock;
 
+#[cfg(all(windows, feature = "windows"))]
 pub use win::*;
+
+use crate::command::Command;
+use crate::config::Binding;
+use crate::monitor::Monitor;
+use crate::tile;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent<W> {
+    Created(W),
+    Destroyed(W),
+    FocusChanged(W),
+    // minimized, hidden or cloaked
+    Hidden(W),
+    // restored, shown or uncloaked
+    Shown(W),
+    // the user finished moving or resizing the window, which now has these dimensions, letting
+    // go of the mouse at the given point
+    Moved(W, tile::Dimensions, (i32, i32)),
+    // the window moved or changed size without the user dragging it, usually the program doing
+    // it itself
+    Relocated(W),
+    // a monitor was connected, disconnected or changed its resolution or work area, these are
+    // all the monitors now
+    MonitorsChanged(Vec<Monitor>),
+    // the cursor moved to this point, only sent once the backend was asked to watch the mouse
+    MouseMoved(i32, i32),
+    Command(Command),
+}
+
+impl<W: Copy> WindowEvent<W> {
+    pub fn window(&self) -> Option<W> {
+        match self {
+            WindowEvent::Created(window)
+            | WindowEvent::Destroyed(window)
+            | WindowEvent::FocusChanged(window)
+            | WindowEvent::Hidden(window)
+            | WindowEvent::Shown(window)
+            | WindowEvent::Moved(window, ..)
+            | WindowEvent::Relocated(window) => Some(*window),
+            WindowEvent::MonitorsChanged(_) | WindowEvent::MouseMoved(..) | WindowEvent::Command(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class: String,
+    pub process: String,
+}
+
+// Describes which windows a rule applies to. Every field that is set has to match: process and
+// class are compared ignoring case, the title only has to contain the given text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Criteria {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Criteria {
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        let process = match &self.process {
+            Some(process) => process.eq_ignore_ascii_case(&info.process),
+            None => true,
+        };
+        let class = match &self.class {
+            Some(class) => class.eq_ignore_ascii_case(&info.class),
+            None => true,
+        };
+        let title = match &self.title {
+            Some(title) => info.title.contains(title.as_str()),
+            None => true,
+        };
+        process && class && title
+    }
+}
+
+// Window handles have to survive being written to disk (traces, saved layouts), so every
+// backend handle needs a stable integer form.
+pub trait Handle: Copy + PartialEq + std::fmt::Debug {
+    fn id(&self) -> usize;
+    fn from_id(id: usize) -> Self;
+}
+
+impl Handle for usize {
+    fn id(&self) -> usize {
+        *self
+    }
+
+    fn from_id(id: usize) -> Self {
+        id
+    }
+}
+
+pub trait Backend {
+    type Window: Handle;
+
+    // gives back a message for each binding that couldn't be set up
+    fn create_hooks(&mut self, bindings: &[Binding]) -> Vec<String>;
+    // starts sending MouseMoved events
+    fn watch_mouse(&mut self);
+    fn next_event(&mut self) -> Option<WindowEvent<Self::Window>>;
+    // blocks until there might be another event, or for the timeout at most
+    fn wait(&mut self, timeout: Option<Duration>);
+    fn get_active_window(&self) -> Option<Self::Window>;
+    fn get_initial_windows(&self) -> Vec<Self::Window>;
+    // the monitor's work area, what is left of it once the taskbar has its space
+    fn get_window_dimensions(&self) -> tile::Dimensions;
+    // the whole monitor, including anything reserved for bars
+    fn get_monitor_dimensions(&self) -> tile::Dimensions;
+    // every connected monitor, the primary one first
+    fn get_monitors(&self) -> Vec<Monitor>;
+    fn get_window_info(&self, window: Self::Window) -> WindowInfo;
+    // how small and how big the window lets itself be made, measured like its visible frame
+    fn get_size_hints(&self, window: Self::Window) -> tile::SizeHints;
+    // where the window's visible frame is now
+    fn get_window_rect(&self, window: Self::Window) -> Option<tile::Dimensions>;
+    fn show_window(&mut self, window: Self::Window);
+    fn hide_window(&mut self, window: Self::Window);
+    fn focus_window(&mut self, window: Self::Window);
+    fn get_cursor_pos(&self) -> Option<(i32, i32)>;
+    fn move_cursor(&mut self, x: i32, y: i32);
+    // starts a program from a command line, without waiting for it. Paths with spaces are in
+    // double quotes.
+    fn launch(&mut self, command_line: &str) -> Result<(), String>;
+    fn set_window_pos(&mut self, window: Self::Window, x: i32, y: i32, cx: i32, cy: i32) -> bool;
+    // moves all the windows in one go, so none of them is drawn in its new place before the rest
+    fn set_window_positions(&mut self, positions: &[(Self::Window, tile::Dimensions)]);
+}
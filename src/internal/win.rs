@@ -1,82 +1,414 @@
+use super::{Backend, Handle, WindowEvent, WindowInfo};
+use crate::command::Command;
+use crate::config::{Binding, Key, Keys};
+use crate::core::clock::SystemClock;
+use crate::core::timer::Timers;
+use crate::monitor::{self, Monitor};
+use crate::scratchpad;
 use crate::tile;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
 use winapi::{
     ctypes,
     shared::{minwindef, windef, winerror},
-    um::{dwmapi, winnt, winuser},
+    um::{consoleapi, dwmapi, handleapi, processthreadsapi, shellscalingapi, winbase, winnt, winuser},
 };
 
-pub enum WindowEvent {
-    Created(windef::HWND),
-    Destroyed(windef::HWND),
-    FocusChanged(windef::HWND),
-    OrientationChanged
+// how often monitors are checked for changes, display changes and the taskbar moving or starting
+// to hide itself are only sent to programs with a window
+const MONITORS_INTERVAL: Duration = Duration::from_millis(250);
+// how often the cursor is looked at while it's watched
+const CURSOR_INTERVAL: Duration = Duration::from_millis(15);
+
+// filled by the hooks while messages are dispatched, several can come in for a single message
+static mut WIN_EVENTS: VecDeque<WindowEvent<windef::HWND>> = VecDeque::new();
+// whether the user is moving or resizing a window right now
+static mut MOVING: bool = false;
+// the console was closed or interrupted, set from the thread the console calls us on
+static QUIT: AtomicBool = AtomicBool::new(false);
+// every hidden window was shown again, so the process can be let go
+static RELEASED: AtomicBool = AtomicBool::new(false);
+// the thread running the loop, so closing the console can wake it up
+static MAIN_THREAD: AtomicU32 = AtomicU32::new(0);
+// how long closing the console waits for hidden windows to be shown before giving up
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct WinBackend {
+    // command for each registered hot key, indexed by hot key id
+    commands: Vec<Command>,
+    // last cursor position seen, None until the mouse is watched
+    cursor: Option<(i32, i32)>,
+    monitors: Vec<Monitor>,
+    monitors_poll: Timers<()>,
 }
 
-pub static mut WIN_EVENT: Option<WindowEvent> = None;
+impl WinBackend {
+    pub fn new() -> WinBackend {
+        // positions and sizes are physical pixels on every monitor instead of being scaled by
+        // whichever DPI windows guesses for us
+        unsafe {
+            winuser::SetProcessDpiAwarenessContext(windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+        }
+        let mut monitors_poll = Timers::new(Box::new(SystemClock));
+        monitors_poll.repeat((), MONITORS_INTERVAL);
+        WinBackend { commands: Vec::new(), cursor: None, monitors: get_monitors(), monitors_poll }
+    }
 
-pub fn create_hooks() {
-    let window_hook_res: windef::HWINEVENTHOOK;
-    let focus_hook_res: windef::HWINEVENTHOOK;
-    unsafe {
-        window_hook_res = winuser::SetWinEventHook(
-            winuser::EVENT_OBJECT_CREATE,
-            winuser::EVENT_OBJECT_DESTROY,
-            std::ptr::null_mut(),
-            Some(window_event_hook),
-            0,
-            0,
-            winuser::WINEVENT_OUTOFCONTEXT,
-        );
+    fn monitors_changed(&mut self) -> Option<WindowEvent<windef::HWND>> {
+        if self.monitors_poll.take_due().is_empty() {
+            return None;
+        }
 
-        if let None = window_hook_res.as_ref() {
-            panic!("Could not set window create & window delete hooks. Aborting");
+        let monitors = get_monitors();
+        if monitors == self.monitors {
+            return None;
         }
+        self.monitors = monitors.clone();
+        Some(WindowEvent::MonitorsChanged(monitors))
+    }
 
-        focus_hook_res = winuser::SetWinEventHook(
-            winuser::EVENT_OBJECT_FOCUS,
-            winuser::EVENT_OBJECT_FOCUS,
-            std::ptr::null_mut(),
-            Some(focus_changed),
-            0,
-            0,
-            winuser::WINEVENT_OUTOFCONTEXT,
-        );
+    // The cursor is polled rather than hooked, a low level mouse hook would fire for every
+    // pixel and could overwrite window events that haven't been handled yet.
+    fn cursor_moved(&mut self) -> Option<WindowEvent<windef::HWND>> {
+        let last = self.cursor?;
+        let current = get_cursor_pos()?;
+        if current == last {
+            return None;
+        }
+        self.cursor = Some(current);
+        Some(WindowEvent::MouseMoved(current.0, current.1))
+    }
+}
+
+// The loop only stops once it has let go of every window, after which the backend goes away.
+impl Drop for WinBackend {
+    fn drop(&mut self) {
+        RELEASED.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Default for WinBackend {
+    fn default() -> WinBackend {
+        WinBackend::new()
+    }
+}
+
+impl Handle for windef::HWND {
+    fn id(&self) -> usize {
+        *self as usize
+    }
+
+    fn from_id(id: usize) -> Self {
+        id as windef::HWND
+    }
+}
 
-        if let None = focus_hook_res.as_ref() {
-            panic!("Could not set focus changed hook. Aborting");
+impl Backend for WinBackend {
+    type Window = windef::HWND;
+
+    fn create_hooks(&mut self, bindings: &[Binding]) -> Vec<String> {
+        create_hooks();
+        let (commands, errors) = register_hotkeys(bindings);
+        self.commands = commands;
+        unsafe {
+            MAIN_THREAD.store(processthreadsapi::GetCurrentThreadId(), Ordering::SeqCst);
+            consoleapi::SetConsoleCtrlHandler(Some(console_closing), minwindef::TRUE);
+        }
+        errors
+    }
+
+    fn watch_mouse(&mut self) {
+        self.cursor = get_cursor_pos();
+    }
+
+    fn next_event(&mut self) -> Option<WindowEvent<windef::HWND>> {
+        if QUIT.swap(false, Ordering::SeqCst) {
+            return Some(WindowEvent::Command(Command::Quit));
+        }
+        send_message(&self.commands)
+            .or_else(|| self.cursor_moved())
+            .or_else(|| self.monitors_changed())
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) {
+        // the monitors and the cursor are polled, so they wake the loop up too
+        let timeouts = [timeout, self.monitors_poll.next_due(), self.cursor.map(|_| CURSOR_INTERVAL)];
+        let timeout = timeouts.iter().flatten().min().copied();
+        let millis = match timeout {
+            // rounded up, so waiting for a timer that's almost due doesn't spin until it is
+            Some(timeout) => timeout.as_micros().div_ceil(1000).min(winbase::INFINITE as u128 - 1) as u32,
+            None => winbase::INFINITE,
+        };
+        unsafe {
+            // hooks may have queued more events than the last message gave back
+            if !(*std::ptr::addr_of!(WIN_EVENTS)).is_empty() {
+                return;
+            }
+            winuser::MsgWaitForMultipleObjectsEx(
+                0,
+                std::ptr::null(),
+                millis,
+                winuser::QS_ALLINPUT,
+                winuser::MWMO_INPUTAVAILABLE,
+            );
         }
+    }
+
+    fn get_active_window(&self) -> Option<windef::HWND> {
+        let hwnd = get_active_window();
+        if hwnd.is_null() {
+            return None;
+        }
+        Some(hwnd)
+    }
+
+    fn get_initial_windows(&self) -> Vec<windef::HWND> {
+        get_initial_windows()
+    }
+
+    fn get_window_dimensions(&self) -> tile::Dimensions {
+        get_window_dimensions()
+    }
+
+    fn get_monitor_dimensions(&self) -> tile::Dimensions {
+        get_monitor_dimensions()
+    }
+
+    fn get_monitors(&self) -> Vec<Monitor> {
+        get_monitors()
+    }
+
+    fn get_window_info(&self, window: windef::HWND) -> WindowInfo {
+        get_window_info(window)
+    }
+
+    fn get_size_hints(&self, window: windef::HWND) -> tile::SizeHints {
+        get_size_hints(window)
+    }
+
+    fn get_window_rect(&self, window: windef::HWND) -> Option<tile::Dimensions> {
+        get_frame_bounds(window).or_else(|| get_window_rect(window))
+    }
 
-        if winuser::RegisterHotKey(std::ptr::null_mut(), 0, winuser::MOD_ALT as minwindef::UINT, 0x58) == minwindef::FALSE {
-            panic!("Could not register hot key");
+    fn show_window(&mut self, window: windef::HWND) {
+        show_window(window);
+    }
+
+    fn hide_window(&mut self, window: windef::HWND) {
+        hide_window(window);
+    }
+
+    fn focus_window(&mut self, window: windef::HWND) {
+        focus_window(window);
+    }
+
+    fn get_cursor_pos(&self) -> Option<(i32, i32)> {
+        get_cursor_pos()
+    }
+
+    fn move_cursor(&mut self, x: i32, y: i32) {
+        unsafe {
+            winuser::SetCursorPos(x, y);
         }
+    }
+
+    fn launch(&mut self, command_line: &str) -> Result<(), String> {
+        launch(command_line)
+    }
+
+    fn set_window_pos(&mut self, window: windef::HWND, x: i32, y: i32, cx: i32, cy: i32) -> bool {
+        set_window_pos(window, x, y, cx, cy)
+    }
+
+    fn set_window_positions(&mut self, positions: &[(windef::HWND, tile::Dimensions)]) {
+        set_window_positions(positions);
+    }
+}
 
+pub fn create_hooks() {
+    let hooks: [(minwindef::DWORD, minwindef::DWORD, winuser::WINEVENTPROC, &str); 7] = [
+        (winuser::EVENT_OBJECT_CREATE, winuser::EVENT_OBJECT_DESTROY, Some(window_event_hook), "window create & window delete"),
+        (winuser::EVENT_OBJECT_FOCUS, winuser::EVENT_OBJECT_FOCUS, Some(focus_changed), "focus changed"),
+        (winuser::EVENT_SYSTEM_MOVESIZESTART, winuser::EVENT_SYSTEM_MOVESIZEEND, Some(move_size_changed), "move & resize"),
+        (winuser::EVENT_OBJECT_LOCATIONCHANGE, winuser::EVENT_OBJECT_LOCATIONCHANGE, Some(location_changed), "location change"),
+        (winuser::EVENT_SYSTEM_MINIMIZESTART, winuser::EVENT_SYSTEM_MINIMIZEEND, Some(visibility_changed), "minimize"),
+        (winuser::EVENT_OBJECT_SHOW, winuser::EVENT_OBJECT_HIDE, Some(visibility_changed), "show & hide"),
+        (winuser::EVENT_OBJECT_CLOAKED, winuser::EVENT_OBJECT_UNCLOAKED, Some(visibility_changed), "cloak"),
+    ];
+
+    for (min, max, hook, name) in hooks {
+        let hook_res = unsafe {
+            winuser::SetWinEventHook(min, max, std::ptr::null_mut(), hook, 0, 0, winuser::WINEVENT_OUTOFCONTEXT)
+        };
+        if hook_res.is_null() {
+            panic!("Could not set {} hook. Aborting", name);
+        }
+    }
+}
 
-        if winuser::RegisterHotKey(std::ptr::null_mut(), 0, winuser::MOD_ALT as minwindef::UINT, 0x43) == minwindef::FALSE {
-            panic!("Could not register hot key");
+// Registers a hot key per binding, using the binding's index as the hot key id. Returns the
+// command for each id, and why the bindings that were left out couldn't be registered.
+pub fn register_hotkeys(bindings: &[Binding]) -> (Vec<Command>, Vec<String>) {
+    let mut commands = Vec::new();
+    let mut errors = Vec::new();
+    for binding in bindings {
+        let id = commands.len() as i32;
+        let registered = unsafe {
+            winuser::RegisterHotKey(
+                std::ptr::null_mut(),
+                id,
+                modifiers(&binding.keys),
+                virtual_key(binding.keys.key),
+            )
+        };
+        if registered == minwindef::FALSE {
+            // usually another program already owns the combination
+            errors.push(format!("could not register hot key for '{}'", binding.command));
+            continue;
         }
+        commands.push(binding.command.clone());
+    }
+    (commands, errors)
+}
+
+fn modifiers(keys: &Keys) -> minwindef::UINT {
+    let mut modifiers = winuser::MOD_NOREPEAT;
+    if keys.alt {
+        modifiers |= winuser::MOD_ALT;
+    }
+    if keys.ctrl {
+        modifiers |= winuser::MOD_CONTROL;
+    }
+    if keys.shift {
+        modifiers |= winuser::MOD_SHIFT;
     }
+    if keys.win {
+        modifiers |= winuser::MOD_WIN;
+    }
+    modifiers as minwindef::UINT
+}
+
+fn virtual_key(key: Key) -> minwindef::UINT {
+    let vk = match key {
+        // letters and digits share their virtual key codes with ASCII
+        Key::Char(c) => c as i32,
+        Key::Function(n) => winuser::VK_F1 + n as i32 - 1,
+        Key::Space => winuser::VK_SPACE,
+        Key::Enter => winuser::VK_RETURN,
+        Key::Tab => winuser::VK_TAB,
+        Key::Escape => winuser::VK_ESCAPE,
+        Key::Left => winuser::VK_LEFT,
+        Key::Right => winuser::VK_RIGHT,
+        Key::Up => winuser::VK_UP,
+        Key::Down => winuser::VK_DOWN,
+    };
+    vk as minwindef::UINT
 }
 
-pub fn send_message() -> Option<WindowEvent> {
+pub fn send_message(commands: &[Command]) -> Option<WindowEvent<windef::HWND>> {
     let mut msg: winuser::MSG = Default::default();
     unsafe {
         let msg_exists =
             winuser::PeekMessageW(&mut msg, std::ptr::null_mut(), 0, 0, winuser::PM_REMOVE);
         if msg_exists == minwindef::TRUE {
-            match msg.message {
-                winuser::WM_HOTKEY => { WIN_EVENT = Some(WindowEvent::OrientationChanged) }
-                _ => { }
+            if msg.message == winuser::WM_HOTKEY {
+                if let Some(command) = commands.get(msg.wParam) {
+                    push_event(WindowEvent::Command(command.clone()))
+                }
             }
 
             winuser::TranslateMessage(&msg);
-            winuser::DispatchMessageW(&mut msg);
+            winuser::DispatchMessageW(&msg);
         }
-        WIN_EVENT.take()
+        (*std::ptr::addr_of_mut!(WIN_EVENTS)).pop_front()
     }
 }
 
+// Only ever called on the main thread, from the hooks or right after dispatching.
+unsafe fn push_event(event: WindowEvent<windef::HWND>) {
+    (*std::ptr::addr_of_mut!(WIN_EVENTS)).push_back(event);
+}
+
 pub fn get_active_window() -> windef::HWND {
-    unsafe { winuser::GetActiveWindow() }
+    // GetActiveWindow only knows about windows of the calling thread
+    unsafe { winuser::GetForegroundWindow() }
+}
+
+// how long a window gets to say how big it can be before it's taken as not caring
+const SIZE_HINTS_TIMEOUT: minwindef::UINT = 50;
+
+fn get_size_hints(hwnd: windef::HWND) -> tile::SizeHints {
+    let mut info: winuser::MINMAXINFO = Default::default();
+    let mut result = 0;
+    let sent = unsafe {
+        winuser::SendMessageTimeoutW(
+            hwnd,
+            winuser::WM_GETMINMAXINFO,
+            0,
+            &mut info as *mut _ as minwindef::LPARAM,
+            winuser::SMTO_ABORTIFHUNG,
+            SIZE_HINTS_TIMEOUT,
+            &mut result,
+        )
+    };
+    if sent == 0 {
+        return tile::SizeHints::default();
+    }
+
+    // the sizes include the invisible borders, tiles are the visible frame
+    let insets = match (get_window_rect(hwnd), get_frame_bounds(hwnd)) {
+        (Some(window), Some(frame)) => monitor::frame_insets(&window, &frame),
+        _ => monitor::Edges::default(),
+    };
+    let (width, height) = (insets.left + insets.right, insets.top + insets.bottom);
+    let max = |value: i32, borders: i32| if value > 0 { value - borders } else { i32::MAX };
+    tile::SizeHints {
+        min: ((info.ptMinTrackSize.x - width).max(0), (info.ptMinTrackSize.y - height).max(0)),
+        max: (max(info.ptMaxTrackSize.x, width), max(info.ptMaxTrackSize.y, height)),
+    }
+}
+
+fn get_window_info(hwnd: windef::HWND) -> WindowInfo {
+    let mut title: Vec<u16> = vec![0; 512];
+    let mut class: Vec<u16> = vec![0; 256];
+    let mut process: Vec<u16> = vec![0; minwindef::MAX_PATH];
+    let mut process_len = 0u32;
+    unsafe {
+        let title_len = winuser::GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32);
+        title.truncate(title_len.max(0) as usize);
+
+        let class_len = winuser::GetClassNameW(hwnd, class.as_mut_ptr(), class.len() as i32);
+        class.truncate(class_len.max(0) as usize);
+
+        let mut pid = 0u32;
+        winuser::GetWindowThreadProcessId(hwnd, &mut pid);
+        let handle = processthreadsapi::OpenProcess(
+            winnt::PROCESS_QUERY_LIMITED_INFORMATION,
+            minwindef::FALSE,
+            pid,
+        );
+        if !handle.is_null() {
+            process_len = process.len() as u32;
+            if winbase::QueryFullProcessImageNameW(handle, 0, process.as_mut_ptr(), &mut process_len)
+                == minwindef::FALSE
+            {
+                process_len = 0;
+            }
+            handleapi::CloseHandle(handle);
+        }
+    }
+    process.truncate(process_len as usize);
+
+    // only keep the executable name, the full path isn't useful for identifying a window
+    let process = String::from_utf16_lossy(&process);
+    let process = process.rsplit('\\').next().unwrap_or_default().to_string();
+
+    WindowInfo {
+        title: String::from_utf16_lossy(&title),
+        class: String::from_utf16_lossy(&class),
+        process,
+    }
 }
 
 pub fn get_initial_windows() -> Vec<windef::HWND> {
@@ -94,12 +426,18 @@ pub fn get_initial_windows() -> Vec<windef::HWND> {
     win_handles
 }
 
-pub fn set_window_pos(hwnd: windef::HWND, x: i32, y: i32, cx: i32, cy: i32) -> bool {
+fn set_window_pos(hwnd: windef::HWND, x: i32, y: i32, cx: i32, cy: i32) -> bool {
     let set_pos_res: minwindef::BOOL;
     unsafe {
         // TODO Removed HWND_TOPMOST during development... evaluate if it we want window
         // to actually be TOPMOST, since it could be annoying once the WM is closed
-        set_pos_res = winuser::SetWindowPos(hwnd, winuser::HWND_TOP, x, y, cx, cy, 0u32);
+        let dpi = winuser::GetDpiForWindow(hwnd);
+        set_pos_res = set_frame_pos(hwnd, x, y, cx, cy);
+        // a window moved onto a monitor with another DPI rescales itself, and its borders, once
+        // it gets there, so it needs to be told its size again
+        if set_pos_res != minwindef::FALSE && winuser::GetDpiForWindow(hwnd) != dpi {
+            set_frame_pos(hwnd, x, y, cx, cy);
+        }
     }
 
     if set_pos_res == minwindef::FALSE {
@@ -109,9 +447,146 @@ pub fn set_window_pos(hwnd: windef::HWND, x: i32, y: i32, cx: i32, cy: i32) -> b
     true
 }
 
+// Moves every window with a single redraw at the end. If the batch can't be set up the windows
+// are moved one at a time instead.
+pub fn set_window_positions(positions: &[(windef::HWND, tile::Dimensions)]) {
+    unsafe {
+        let dpis: Vec<minwindef::UINT> = positions.iter().map(|(hwnd, _)| winuser::GetDpiForWindow(*hwnd)).collect();
+        let mut batch = winuser::BeginDeferWindowPos(positions.len() as i32);
+        for (hwnd, dim) in positions {
+            if batch.is_null() {
+                break;
+            }
+            let dim = frame_rect(*hwnd, dim);
+            batch = winuser::DeferWindowPos(batch, *hwnd, winuser::HWND_TOP, dim.x.0, dim.y.0, dim.x.1, dim.y.1, 0u32);
+        }
+        if batch.is_null() || winuser::EndDeferWindowPos(batch) == minwindef::FALSE {
+            for (hwnd, dim) in positions {
+                set_window_pos(*hwnd, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+            }
+            return;
+        }
+
+        // same as set_window_pos, windows that changed DPI on the way need their size again
+        for ((hwnd, dim), dpi) in positions.iter().zip(dpis) {
+            if winuser::GetDpiForWindow(*hwnd) != dpi {
+                set_frame_pos(*hwnd, dim.x.0, dim.y.0, dim.x.1, dim.y.1);
+            }
+        }
+    }
+}
+
+// Positions hwnd so that its visible frame, rather than its rect with the invisible borders
+// around it, ends up at the given position and size.
+unsafe fn set_frame_pos(hwnd: windef::HWND, x: i32, y: i32, cx: i32, cy: i32) -> minwindef::BOOL {
+    let dim = frame_rect(hwnd, &tile::Dimensions { x: (x, cx), y: (y, cy) });
+    winuser::SetWindowPos(hwnd, winuser::HWND_TOP, dim.x.0, dim.y.0, dim.x.1, dim.y.1, 0u32)
+}
+
+// The rect to give hwnd for its visible frame to fill dim.
+fn frame_rect(hwnd: windef::HWND, dim: &tile::Dimensions) -> tile::Dimensions {
+    match (get_window_rect(hwnd), get_frame_bounds(hwnd)) {
+        (Some(window), Some(frame)) => monitor::grow(dim, &monitor::frame_insets(&window, &frame)),
+        _ => dim.clone(),
+    }
+}
+
+fn get_window_rect(hwnd: windef::HWND) -> Option<tile::Dimensions> {
+    let mut rect: windef::RECT = Default::default();
+    if unsafe { winuser::GetWindowRect(hwnd, &mut rect) } == minwindef::FALSE {
+        return None;
+    }
+    Some(to_dimensions(&rect))
+}
+
+// The rect of the frame as it's drawn, without the invisible borders
+fn get_frame_bounds(hwnd: windef::HWND) -> Option<tile::Dimensions> {
+    let mut rect: windef::RECT = Default::default();
+    let result = unsafe {
+        dwmapi::DwmGetWindowAttribute(
+            hwnd,
+            dwmapi::DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut rect as *mut _ as *mut ctypes::c_void,
+            std::mem::size_of::<windef::RECT>() as u32,
+        )
+    };
+    if result != winerror::S_OK {
+        return None;
+    }
+    Some(to_dimensions(&rect))
+}
+
 pub fn get_window_dimensions() -> tile::Dimensions {
-    let monitor: windef::HMONITOR;
-    monitor = get_primary_monitor();
+    to_dimensions(&get_monitor_info().rcWork)
+}
+
+pub fn get_monitor_dimensions() -> tile::Dimensions {
+    to_dimensions(&get_monitor_info().rcMonitor)
+}
+
+pub fn get_monitors() -> Vec<Monitor> {
+    let mut handles: Vec<windef::HMONITOR> = Vec::new();
+    unsafe {
+        winuser::EnumDisplayMonitors(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            Some(enum_monitors),
+            &mut handles as *mut Vec<windef::HMONITOR> as minwindef::LPARAM,
+        );
+    }
+
+    let mut monitors = Vec::new();
+    for handle in handles {
+        let mut info = winuser::MONITORINFOEXW {
+            cbSize: std::mem::size_of::<winuser::MONITORINFOEXW>() as _,
+            ..Default::default()
+        };
+        let info_res = unsafe { winuser::GetMonitorInfoW(handle, &mut info as *mut _ as winuser::LPMONITORINFO) };
+        if info_res == minwindef::FALSE {
+            continue;
+        }
+
+        let (mut dpi_x, mut dpi_y) = (96, 96);
+        unsafe {
+            shellscalingapi::GetDpiForMonitor(handle, shellscalingapi::MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
+        }
+
+        let len = info.szDevice.iter().position(|c| *c == 0).unwrap_or(info.szDevice.len());
+        let monitor = Monitor {
+            id: String::from_utf16_lossy(&info.szDevice[..len]),
+            area: to_dimensions(&info.rcMonitor),
+            work_area: to_dimensions(&info.rcWork),
+            scale: dpi_x as f32 / 96.0,
+        };
+        if info.dwFlags & winuser::MONITORINFOF_PRIMARY != 0 {
+            monitors.insert(0, monitor);
+        } else {
+            monitors.push(monitor);
+        }
+    }
+    monitors
+}
+
+unsafe extern "system" fn enum_monitors(
+    monitor: windef::HMONITOR,
+    _hdc: windef::HDC,
+    _rect: windef::LPRECT,
+    l_param: minwindef::LPARAM,
+) -> minwindef::BOOL {
+    let handles = &mut *(l_param as *mut Vec<windef::HMONITOR>);
+    handles.push(monitor);
+    minwindef::TRUE
+}
+
+fn to_dimensions(rect: &windef::RECT) -> tile::Dimensions {
+    tile::Dimensions {
+        x: (rect.left, rect.right - rect.left),
+        y: (rect.top, rect.bottom - rect.top),
+    }
+}
+
+fn get_monitor_info() -> winuser::MONITORINFO {
+    let monitor = get_primary_monitor();
     let mut monitor_info = winuser::MONITORINFO {
         cbSize: std::mem::size_of::<winuser::MONITORINFO>() as _,
         ..Default::default()
@@ -126,18 +601,58 @@ pub fn get_window_dimensions() -> tile::Dimensions {
         panic!("Could not retrieve monitor information.");
     }
 
-    tile::Dimensions {
-        x: (monitor_info.rcMonitor.left, monitor_info.rcMonitor.right),
-        y: (monitor_info.rcMonitor.top, monitor_info.rcMonitor.bottom),
-    }
+    monitor_info
 }
 
-pub fn show_window(hwnd: windef::HWND) {
+fn show_window(hwnd: windef::HWND) {
     unsafe {
         winuser::ShowWindow(hwnd, winuser::SW_RESTORE);
     }
 }
 
+fn hide_window(hwnd: windef::HWND) {
+    unsafe {
+        winuser::ShowWindow(hwnd, winuser::SW_HIDE);
+    }
+}
+
+fn focus_window(hwnd: windef::HWND) {
+    unsafe {
+        winuser::SetForegroundWindow(hwnd);
+    }
+}
+
+pub fn get_cursor_pos() -> Option<(i32, i32)> {
+    let mut point: windef::POINT = Default::default();
+    if unsafe { winuser::GetCursorPos(&mut point) } == minwindef::FALSE {
+        return None;
+    }
+    Some((point.x, point.y))
+}
+
+pub fn launch(command_line: &str) -> Result<(), String> {
+    let mut words = scratchpad::split_words(command_line).into_iter().map(|(_, word)| word);
+    let program = words.next().ok_or_else(|| "nothing to launch".to_string())?;
+    match std::process::Command::new(&program).args(words).spawn() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("could not launch '{}': {}", command_line, e)),
+    }
+}
+
+// Ctrl+C, closing the console window, logging off and shutting down all end up here. The
+// process is ended as soon as this returns, so it waits a little for the loop to quit first.
+unsafe extern "system" fn console_closing(_ctrl_type: minwindef::DWORD) -> minwindef::BOOL {
+    QUIT.store(true, Ordering::SeqCst);
+    winuser::PostThreadMessageW(MAIN_THREAD.load(Ordering::SeqCst), winuser::WM_NULL, 0, 0);
+    let step = Duration::from_millis(20);
+    let mut waited = Duration::ZERO;
+    while !RELEASED.load(Ordering::SeqCst) && waited < RELEASE_TIMEOUT {
+        std::thread::sleep(step);
+        waited += step;
+    }
+    minwindef::TRUE
+}
+
 unsafe extern "system" fn focus_changed(
     _event_hook: windef::HWINEVENTHOOK,
     _event: minwindef::DWORD,
@@ -147,7 +662,82 @@ unsafe extern "system" fn focus_changed(
     _id_event_thread: minwindef::DWORD,
     _time: minwindef::DWORD,
 ) {
-    WIN_EVENT = Some(WindowEvent::FocusChanged(hwnd));
+    push_event(WindowEvent::FocusChanged(hwnd));
+}
+
+unsafe extern "system" fn visibility_changed(
+    _event_hook: windef::HWINEVENTHOOK,
+    event: minwindef::DWORD,
+    hwnd: windef::HWND,
+    id_obj: winnt::LONG,
+    id_child: winnt::LONG,
+    _id_event_thread: minwindef::DWORD,
+    _time: minwindef::DWORD,
+) {
+    // show and hide also fire for carets, cursors and the parts of a window
+    if id_obj != winuser::OBJID_WINDOW || id_child != winuser::INDEXID_CONTAINER {
+        return;
+    }
+
+    push_event(match event {
+        winuser::EVENT_SYSTEM_MINIMIZESTART | winuser::EVENT_OBJECT_HIDE | winuser::EVENT_OBJECT_CLOAKED => {
+            WindowEvent::Hidden(hwnd)
+        }
+        winuser::EVENT_SYSTEM_MINIMIZEEND | winuser::EVENT_OBJECT_SHOW | winuser::EVENT_OBJECT_UNCLOAKED => {
+            WindowEvent::Shown(hwnd)
+        }
+        _ => return,
+    });
+}
+
+unsafe extern "system" fn move_size_changed(
+    _event_hook: windef::HWINEVENTHOOK,
+    event: minwindef::DWORD,
+    hwnd: windef::HWND,
+    _id_obj: winnt::LONG,
+    _id_child: winnt::LONG,
+    _id_event_thread: minwindef::DWORD,
+    _time: minwindef::DWORD,
+) {
+    MOVING = event == winuser::EVENT_SYSTEM_MOVESIZESTART;
+    if MOVING {
+        return;
+    }
+
+    // the visible frame, which is what windows are tiled by
+    let dim = match get_frame_bounds(hwnd).or_else(|| get_window_rect(hwnd)) {
+        Some(dim) => dim,
+        None => return,
+    };
+
+    let cursor = match get_cursor_pos() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    push_event(WindowEvent::Moved(hwnd, dim, cursor));
+}
+
+unsafe extern "system" fn location_changed(
+    _event_hook: windef::HWINEVENTHOOK,
+    _event: minwindef::DWORD,
+    hwnd: windef::HWND,
+    id_obj: winnt::LONG,
+    id_child: winnt::LONG,
+    _id_event_thread: minwindef::DWORD,
+    _time: minwindef::DWORD,
+) {
+    // the user dragging a window is reported once they let go, and the caret and cursor moving
+    // don't matter
+    if MOVING || id_obj != winuser::OBJID_WINDOW || id_child != winuser::INDEXID_CONTAINER {
+        return;
+    }
+    // these come in for every step of a window moving, including the ones moved by redrawing,
+    // only the latest position matters so one per window is enough
+    let relocated = WindowEvent::Relocated(hwnd);
+    if !(*std::ptr::addr_of!(WIN_EVENTS)).contains(&relocated) {
+        push_event(relocated);
+    }
 }
 
 unsafe extern "system" fn window_event_hook(
@@ -200,11 +790,11 @@ unsafe extern "system" fn window_event_hook(
     }
 
     if event == EVENT_OBJECT_CREATE {
-        WIN_EVENT = Some(WindowEvent::Created(hwnd));
+        push_event(WindowEvent::Created(hwnd));
     }
 
     if event == EVENT_OBJECT_DESTROY {
-        WIN_EVENT = Some(WindowEvent::Destroyed(hwnd));
+        push_event(WindowEvent::Destroyed(hwnd));
     }
 }
 
@@ -0,0 +1,302 @@
+use super::{Layout, LayoutNode};
+use crate::core::clock::Clock;
+use crate::core::State;
+use crate::internal::{Handle, WindowInfo};
+use crate::tile;
+use crate::workspace::{Workspace, Zoom};
+use std::collections::HashMap;
+
+// Rebuilds a saved layout around the windows that are alive right now. Saved windows are
+// matched by handle first, then by process, class and title. Slots nobody matched are dropped
+// from the tree, and live windows that didn't match a slot are returned so they can be tiled
+// the usual way. Floating, minimized and scratchpad windows come back the same way.
+pub fn adopt<W: Handle>(
+    layout: &Layout,
+    live: &[(W, WindowInfo)],
+    dim: &tile::Dimensions,
+    clock: Box<dyn Clock>,
+) -> (State<W>, Vec<W>) {
+    let matches = match_windows(&saved_windows(layout), live);
+
+    let workspaces = layout
+        .workspaces
+        .iter()
+        .map(|saved| {
+            let mut workspace = Workspace::new(&saved.name, dim.clone());
+            if let Some(mut root) = keep(&saved.root, &matches, &mut workspace) {
+                root.dim = dim.clone();
+                tile::resize_children(&mut root);
+                workspace.root = root;
+            }
+            for node in &saved.floating {
+                if let Some(mut node) = keep(node, &matches, &mut workspace) {
+                    tile::resize_children(&mut node);
+                    workspace.floating.push(node);
+                }
+            }
+            for minimized in &saved.minimized {
+                if let Some(window) = matches.get(&minimized.window.id) {
+                    workspace.minimized.push((*window, (&minimized.place).into()));
+                }
+            }
+            if let Some(focus) = saved.focus.and_then(|id| matches.get(&id).copied()) {
+                workspace.remember_focus(focus);
+            }
+            workspace.auto_balance = saved.auto_balance;
+            workspace.zoom = saved
+                .zoom
+                .as_ref()
+                .and_then(|zoom| matches.get(&zoom.window).map(|window| Zoom { window: *window, area: zoom.area }))
+                .filter(|zoom| tile::windows(&workspace.root).contains(&zoom.window));
+            workspace
+        })
+        .collect::<Vec<_>>();
+
+    let unmatched = live
+        .iter()
+        .map(|(window, _)| *window)
+        .filter(|window| !matches.values().any(|matched| matched == window))
+        .collect();
+
+    let mut state = State::new(workspaces, clock);
+    state.current_workspace = layout.current_workspace.min(state.workspaces.len().saturating_sub(1));
+    state.orientation = layout.orientation;
+    for scratch in &layout.scratchpad {
+        if let Some(window) = matches.get(&scratch.window.id) {
+            state.scratchpad.add(*window, scratch.name.clone(), scratch.visible);
+        }
+    }
+    (state, unmatched)
+}
+
+// Every window the layout knows about, wherever it is.
+pub fn saved_windows(layout: &Layout) -> Vec<(usize, WindowInfo)> {
+    let mut windows = Vec::new();
+    for workspace in &layout.workspaces {
+        collect_windows(&workspace.root, &mut windows);
+        for node in &workspace.floating {
+            collect_windows(node, &mut windows);
+        }
+        windows.extend(workspace.minimized.iter().map(|minimized| (minimized.window.id, minimized.window.info())));
+    }
+    windows.extend(layout.scratchpad.iter().map(|scratch| (scratch.window.id, scratch.window.info())));
+    windows
+}
+
+fn collect_windows(node: &LayoutNode, windows: &mut Vec<(usize, WindowInfo)>) {
+    match node {
+        LayoutNode::Container { children, .. } => {
+            for child in children {
+                collect_windows(child, windows);
+            }
+        }
+        LayoutNode::Window { id, title, class, process, .. } => {
+            windows.push((*id, WindowInfo {
+                title: title.clone(),
+                class: class.clone(),
+                process: process.clone(),
+            }));
+        }
+        LayoutNode::Placeholder { .. } | LayoutNode::Empty { .. } => (),
+    }
+}
+
+pub fn match_windows<W: Handle>(saved: &[(usize, WindowInfo)], live: &[(W, WindowInfo)]) -> HashMap<usize, W> {
+    let mut matches: HashMap<usize, W> = HashMap::new();
+    let mut taken = vec![false; live.len()];
+
+    // handles are recycled by the OS, so a handle only counts if it still belongs to the same
+    // kind of window
+    for (id, info) in saved {
+        let found = live.iter().position(|(window, live_info)| {
+            window.id() == *id && live_info.process == info.process && live_info.class == info.class
+        });
+        if let Some(i) = found {
+            if !taken[i] {
+                taken[i] = true;
+                matches.insert(*id, live[i].0);
+            }
+        }
+    }
+
+    for (id, info) in saved {
+        if matches.contains_key(id) {
+            continue;
+        }
+        let found = live
+            .iter()
+            .enumerate()
+            .position(|(i, (_, live_info))| !taken[i] && live_info == info);
+        if let Some(i) = found {
+            taken[i] = true;
+            matches.insert(*id, live[i].0);
+        }
+    }
+
+    matches
+}
+
+// Converts a saved node into a tree of live windows, dropping windows that were not matched.
+// A container that loses a child is replaced by its remaining child. Placeholders that were
+// still waiting for a window are kept.
+fn keep<W: Handle>(
+    node: &LayoutNode,
+    matches: &HashMap<usize, W>,
+    workspace: &mut Workspace<W>,
+) -> Option<tile::Node<W>> {
+    match node {
+        LayoutNode::Container { orientation, ratio, rect, children } => {
+            let first = children.first().and_then(|child| keep(child, matches, workspace));
+            let second = children.get(1).and_then(|child| keep(child, matches, workspace));
+            match (first, second) {
+                (Some(first), Some(second)) => Some(tile::Node {
+                    node_type: tile::NodeType::Separator(*orientation, *ratio, Box::new(first), Box::new(second)),
+                    dim: rect.into(),
+                }),
+                (Some(only), None) | (None, Some(only)) => Some(only),
+                (None, None) => None,
+            }
+        }
+        LayoutNode::Window { id, rect, .. } => matches.get(id).map(|window| tile::Node {
+            node_type: tile::NodeType::Window(*window),
+            dim: rect.into(),
+        }),
+        LayoutNode::Placeholder { rect, criteria } => Some(tile::Node {
+            node_type: tile::NodeType::Placeholder(workspace.add_slot(criteria.clone())),
+            dim: rect.into(),
+        }),
+        LayoutNode::Empty { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+    use crate::layout;
+
+    fn info(process: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            title: title.to_string(),
+            class: "Class".to_string(),
+            process: process.to_string(),
+        }
+    }
+
+    fn saved_info(window: usize) -> WindowInfo {
+        match window {
+            1 => info("code.exe", "main.rs"),
+            2 => info("cmd.exe", "cmd"),
+            _ => info("notepad.exe", "notes"),
+        }
+    }
+
+    fn dim() -> tile::Dimensions {
+        tile::Dimensions { x: (0, 1920), y: (0, 1080) }
+    }
+
+    // 1 on the left, 2 above 3 on the right
+    fn saved_layout() -> Layout {
+        let mut workspace = Workspace::new("1", dim());
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 1);
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 2);
+        if let tile::NodeType::Separator(_, _, _, right) = &mut workspace.root.node_type {
+            tile::tile(right, tile::Orientation::Horizontal, 3);
+        }
+        workspace.focus = Some(2);
+        let state = State::new(vec![workspace], Box::new(FakeClock::default()));
+        layout::capture(&state, &saved_info)
+    }
+
+    #[test]
+    fn match_windows_should_prefer_handles_over_metadata() {
+        let saved = vec![(1, info("cmd.exe", "cmd")), (2, info("cmd.exe", "cmd"))];
+        let live = vec![(2, info("cmd.exe", "cmd")), (1, info("cmd.exe", "cmd"))];
+
+        let matches = match_windows(&saved, &live);
+
+        assert_eq!(Some(&1), matches.get(&1));
+        assert_eq!(Some(&2), matches.get(&2));
+    }
+
+    #[test]
+    fn match_windows_should_ignore_recycled_handles() {
+        let saved = vec![(1, info("code.exe", "main.rs"))];
+        let live = vec![(1, info("cmd.exe", "cmd")), (7, info("code.exe", "main.rs"))];
+
+        let matches = match_windows(&saved, &live);
+
+        assert_eq!(Some(&7), matches.get(&1));
+    }
+
+    #[test]
+    fn adopt_should_match_restarted_windows_by_metadata() {
+        let live = vec![(11, saved_info(1)), (12, saved_info(2)), (13, saved_info(3))];
+
+        let (state, unmatched) = adopt(&saved_layout(), &live, &dim(), Box::new(FakeClock::default()));
+
+        let mut expected = Workspace::new("1", dim());
+        tile::tile(&mut expected.root, tile::Orientation::Vertical, 11);
+        tile::tile(&mut expected.root, tile::Orientation::Vertical, 12);
+        if let tile::NodeType::Separator(_, _, _, right) = &mut expected.root.node_type {
+            tile::tile(right, tile::Orientation::Horizontal, 13);
+        }
+        assert_eq!(expected.root, state.workspace().root);
+        assert_eq!(Some(12), state.workspace().focus);
+        assert!(unmatched.is_empty());
+    }
+
+    #[test]
+    fn adopt_should_drop_unmatched_slots_and_return_unmatched_windows() {
+        let live = vec![(1, saved_info(1)), (3, saved_info(3)), (4, info("explorer.exe", "Downloads"))];
+
+        let (state, unmatched) = adopt(&saved_layout(), &live, &dim(), Box::new(FakeClock::default()));
+
+        // 2 is gone, so 3 takes over the whole right half
+        let root = &state.workspace().root;
+        if let tile::NodeType::Separator(tile::Orientation::Vertical, _, left, right) = &root.node_type {
+            assert_eq!(tile::NodeType::Window(1), left.node_type);
+            assert_eq!(tile::NodeType::Window(3), right.node_type);
+            assert_eq!(tile::Dimensions { x: (961, 960), y: (0, 1080) }, right.dim);
+        } else {
+            panic!("Root is not a vertical separator");
+        }
+        assert_eq!(None, state.workspace().focus);
+        assert_eq!(vec![4], unmatched);
+    }
+
+    #[test]
+    fn adopt_should_bring_back_floating_minimized_and_scratchpad_windows() {
+        let mut workspace = Workspace::new("1", dim());
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 1);
+        tile::tile(&mut workspace.root, tile::Orientation::Vertical, 2);
+        let place = workspace.place(2).unwrap();
+        workspace.remove_window(2);
+        workspace.minimized.push((2, place.clone()));
+        let floating = tile::Node { node_type: tile::NodeType::Window(3), dim: tile::Dimensions { x: (50, 400), y: (50, 300) } };
+        workspace.floating.push(floating);
+        let mut state = State::new(vec![workspace], Box::new(FakeClock::default()));
+        state.scratchpad.add(4, None, false);
+        let layout = layout::capture(&state, &saved_info);
+        let live = vec![(11, saved_info(1)), (12, saved_info(2)), (13, saved_info(3)), (4, saved_info(4))];
+
+        let (state, unmatched) = adopt(&layout, &live, &dim(), Box::new(FakeClock::default()));
+
+        let workspace = state.workspace();
+        assert_eq!(vec![(12, place)], workspace.minimized);
+        assert_eq!(vec![13], tile::windows(&workspace.floating[0]));
+        assert_eq!(tile::Dimensions { x: (50, 400), y: (50, 300) }, workspace.floating[0].dim);
+        assert_eq!(vec![4], state.scratchpad.windows.iter().map(|scratch| scratch.window).collect::<Vec<_>>());
+        assert!(unmatched.is_empty());
+    }
+
+    #[test]
+    fn adopt_should_leave_workspace_empty_when_nothing_matches() {
+        let live = vec![(4, info("explorer.exe", "Downloads"))];
+
+        let (state, unmatched) = adopt(&saved_layout(), &live, &dim(), Box::new(FakeClock::default()));
+
+        assert_eq!(tile::NodeType::Empty, state.workspace().root.node_type);
+        assert_eq!(vec![4], unmatched);
+    }
+}
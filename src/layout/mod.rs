@@ -0,0 +1,457 @@
+pub mod adopt;
+pub mod template;
+
+use crate::core::clock::Clock;
+use crate::core::State;
+use crate::internal::{Criteria, Handle, WindowInfo};
+use crate::tile;
+use crate::workspace::{Place, Workspace, Zoom, ZoomArea};
+use serde::{Deserialize, Serialize};
+
+// Bump this whenever the JSON shape changes, and have from_json bring older documents up to
+// date.
+pub const VERSION: u32 = 1;
+
+// Stable JSON representation of the whole layout state. This is deliberately separate from
+// `tile::Node` so the tree can change shape without breaking saved layouts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub version: u32,
+    pub orientation: tile::Orientation,
+    pub current_workspace: usize,
+    pub workspaces: Vec<WorkspaceLayout>,
+    pub scratchpad: Vec<ScratchLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceLayout {
+    pub name: String,
+    pub focus: Option<usize>,
+    #[serde(default)]
+    pub auto_balance: bool,
+    pub root: LayoutNode,
+    // subtrees drawn over the tree, at their own rect
+    pub floating: Vec<LayoutNode>,
+    pub minimized: Vec<MinimizedLayout>,
+    pub zoom: Option<ZoomLayout>,
+}
+
+// A window kept outside of any tree, with what's needed to recognize it again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedWindow {
+    pub id: usize,
+    pub title: String,
+    pub class: String,
+    pub process: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinimizedLayout {
+    pub window: SavedWindow,
+    pub place: PlaceLayout,
+}
+
+// Where a minimized window goes back to, see `workspace::Place`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlaceLayout {
+    Tiled {
+        path: Vec<usize>,
+        orientation: tile::Orientation,
+        ratio: f32,
+    },
+    Floating {
+        rect: Rect,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoomLayout {
+    pub window: usize,
+    pub area: ZoomArea,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScratchLayout {
+    pub window: SavedWindow,
+    pub name: Option<String>,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayoutNode {
+    Container {
+        orientation: tile::Orientation,
+        ratio: f32,
+        rect: Rect,
+        children: Vec<LayoutNode>,
+    },
+    Window {
+        id: usize,
+        rect: Rect,
+        title: String,
+        class: String,
+        process: String,
+    },
+    Placeholder {
+        rect: Rect,
+        #[serde(rename = "match")]
+        criteria: Criteria,
+    },
+    Empty {
+        rect: Rect,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<&tile::Dimensions> for Rect {
+    fn from(dim: &tile::Dimensions) -> Rect {
+        Rect {
+            x: dim.x.0,
+            y: dim.y.0,
+            width: dim.x.1,
+            height: dim.y.1,
+        }
+    }
+}
+
+impl From<&Rect> for tile::Dimensions {
+    fn from(rect: &Rect) -> tile::Dimensions {
+        tile::Dimensions {
+            x: (rect.x, rect.width),
+            y: (rect.y, rect.height),
+        }
+    }
+}
+
+impl From<&Place> for PlaceLayout {
+    fn from(place: &Place) -> PlaceLayout {
+        match place {
+            Place::Tiled(spot) => PlaceLayout::Tiled {
+                path: spot.path.clone(),
+                orientation: spot.orientation,
+                ratio: spot.ratio,
+            },
+            Place::Floating(dim) => PlaceLayout::Floating { rect: dim.into() },
+        }
+    }
+}
+
+impl From<&PlaceLayout> for Place {
+    fn from(place: &PlaceLayout) -> Place {
+        match place {
+            PlaceLayout::Tiled { path, orientation, ratio } => Place::Tiled(tile::Spot {
+                path: path.clone(),
+                orientation: *orientation,
+                ratio: *ratio,
+            }),
+            PlaceLayout::Floating { rect } => Place::Floating(rect.into()),
+        }
+    }
+}
+
+impl SavedWindow {
+    pub fn capture<W: Handle>(window: W, info: &dyn Fn(W) -> WindowInfo) -> SavedWindow {
+        let WindowInfo { title, class, process } = info(window);
+        SavedWindow { id: window.id(), title, class, process }
+    }
+
+    pub fn info(&self) -> WindowInfo {
+        WindowInfo {
+            title: self.title.clone(),
+            class: self.class.clone(),
+            process: self.process.clone(),
+        }
+    }
+}
+
+pub fn capture<W: Handle>(state: &State<W>, info: &dyn Fn(W) -> WindowInfo) -> Layout {
+    Layout {
+        version: VERSION,
+        orientation: state.orientation,
+        current_workspace: state.current_workspace,
+        workspaces: state
+            .workspaces
+            .iter()
+            .map(|workspace| WorkspaceLayout {
+                name: workspace.name.clone(),
+                focus: workspace.focus.map(|window| window.id()),
+                auto_balance: workspace.auto_balance,
+                root: capture_node(&workspace.root, workspace, info),
+                floating: workspace.floating.iter().map(|node| capture_node(node, workspace, info)).collect(),
+                minimized: workspace
+                    .minimized
+                    .iter()
+                    .map(|(window, place)| MinimizedLayout {
+                        window: SavedWindow::capture(*window, info),
+                        place: place.into(),
+                    })
+                    .collect(),
+                zoom: workspace.zoom.map(|zoom| ZoomLayout { window: zoom.window.id(), area: zoom.area }),
+            })
+            .collect(),
+        scratchpad: state
+            .scratchpad
+            .windows
+            .iter()
+            .map(|scratch| ScratchLayout {
+                window: SavedWindow::capture(scratch.window, info),
+                name: scratch.name.clone(),
+                visible: scratch.visible,
+            })
+            .collect(),
+    }
+}
+
+fn capture_node<W: Handle>(
+    node: &tile::Node<W>,
+    workspace: &Workspace<W>,
+    info: &dyn Fn(W) -> WindowInfo,
+) -> LayoutNode {
+    let rect = Rect::from(&node.dim);
+    match &node.node_type {
+        tile::NodeType::Separator(orientation, ratio, left, right) => LayoutNode::Container {
+            orientation: *orientation,
+            ratio: *ratio,
+            rect,
+            children: vec![capture_node(left, workspace, info), capture_node(right, workspace, info)],
+        },
+        tile::NodeType::Window(window) => {
+            let WindowInfo { title, class, process } = info(*window);
+            LayoutNode::Window {
+                id: window.id(),
+                rect,
+                title,
+                class,
+                process,
+            }
+        }
+        tile::NodeType::Placeholder(slot) => LayoutNode::Placeholder {
+            rect,
+            criteria: workspace.slots.get(slot).cloned().unwrap_or_default(),
+        },
+        tile::NodeType::Empty => LayoutNode::Empty { rect },
+    }
+}
+
+impl Layout {
+    pub fn to_state<W: Handle>(&self, clock: Box<dyn Clock>) -> Result<State<W>, String> {
+        let mut workspaces = Vec::new();
+        for saved in &self.workspaces {
+            let mut workspace = Workspace::new(&saved.name, saved.root.rect().into());
+            workspace.root = saved.root.to_node(&mut workspace)?;
+            if let Some(focus) = saved.focus {
+                workspace.remember_focus(W::from_id(focus));
+            }
+            workspace.auto_balance = saved.auto_balance;
+            for node in &saved.floating {
+                let node = node.to_node(&mut workspace)?;
+                workspace.floating.push(node);
+            }
+            for minimized in &saved.minimized {
+                workspace.minimized.push((W::from_id(minimized.window.id), (&minimized.place).into()));
+            }
+            workspace.zoom = saved.zoom.as_ref().map(|zoom| Zoom { window: W::from_id(zoom.window), area: zoom.area });
+            workspaces.push(workspace);
+        }
+
+        if self.current_workspace >= workspaces.len() {
+            return Err(format!("current workspace {} does not exist", self.current_workspace));
+        }
+
+        let mut state = State::new(workspaces, clock);
+        state.current_workspace = self.current_workspace;
+        state.orientation = self.orientation;
+        for scratch in &self.scratchpad {
+            state.scratchpad.add(W::from_id(scratch.window.id), scratch.name.clone(), scratch.visible);
+        }
+        Ok(state)
+    }
+}
+
+impl LayoutNode {
+    pub fn rect(&self) -> &Rect {
+        match self {
+            LayoutNode::Container { rect, .. }
+            | LayoutNode::Window { rect, .. }
+            | LayoutNode::Placeholder { rect, .. }
+            | LayoutNode::Empty { rect } => rect,
+        }
+    }
+
+    pub fn to_node<W: Handle>(&self, workspace: &mut Workspace<W>) -> Result<tile::Node<W>, String> {
+        let node = match self {
+            LayoutNode::Container { orientation, ratio, rect, children } => {
+                if children.len() != 2 {
+                    return Err(format!("containers need exactly 2 children, found {}", children.len()));
+                }
+                if !(0.0..=1.0).contains(ratio) {
+                    return Err(format!("container ratio {} is not between 0 and 1", ratio));
+                }
+                tile::Node {
+                    node_type: tile::NodeType::Separator(
+                        *orientation,
+                        *ratio,
+                        Box::new(children[0].to_node(workspace)?),
+                        Box::new(children[1].to_node(workspace)?),
+                    ),
+                    dim: rect.into(),
+                }
+            }
+            LayoutNode::Window { id, rect, .. } => tile::Node {
+                node_type: tile::NodeType::Window(W::from_id(*id)),
+                dim: rect.into(),
+            },
+            LayoutNode::Placeholder { rect, criteria } => tile::Node {
+                node_type: tile::NodeType::Placeholder(workspace.add_slot(criteria.clone())),
+                dim: rect.into(),
+            },
+            LayoutNode::Empty { rect } => tile::Node {
+                node_type: tile::NodeType::Empty,
+                dim: rect.into(),
+            },
+        };
+        Ok(node)
+    }
+}
+
+pub fn to_json(layout: &Layout) -> String {
+    serde_json::to_string_pretty(layout).expect("layout should always be serializable")
+}
+
+pub fn from_json(json: &str) -> Result<Layout, String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let version = match value.get("version").and_then(|v| v.as_u64()) {
+        Some(version) => version as u32,
+        None => return Err("layout has no version".to_string()),
+    };
+    if version > VERSION {
+        return Err(format!("layout version {} is newer than supported version {}", version, VERSION));
+    }
+
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::clock::FakeClock;
+
+    fn info(window: usize) -> WindowInfo {
+        WindowInfo {
+            title: format!("window {}", window),
+            class: "Notepad".to_string(),
+            process: "notepad.exe".to_string(),
+        }
+    }
+
+    fn state() -> State<usize> {
+        let mut first = Workspace::new("1", tile::Dimensions { x: (0, 1920), y: (0, 1080) });
+        tile::tile(&mut first.root, tile::Orientation::Vertical, 1);
+        tile::tile(&mut first.root, tile::Orientation::Vertical, 2);
+        if let tile::NodeType::Separator(_, _, _, right) = &mut first.root.node_type {
+            tile::tile(right, tile::Orientation::Horizontal, 3);
+        }
+        if let tile::NodeType::Separator(_, ratio, _, _) = &mut first.root.node_type {
+            *ratio = 0.6;
+        }
+        tile::resize_children(&mut first.root);
+        first.focus = Some(3);
+
+        let mut state = State::new(vec![first, Workspace::new("2", tile::Dimensions { x: (0, 1920), y: (0, 1080) })], Box::new(FakeClock::default()));
+        state.current_workspace = 1;
+        state.orientation = tile::Orientation::Vertical;
+        state
+    }
+
+    #[test]
+    fn layout_should_round_trip_through_json() {
+        let state = state();
+
+        let json = to_json(&capture(&state, &info));
+        let restored: State<usize> = from_json(&json).unwrap().to_state(Box::new(FakeClock::default())).unwrap();
+
+        assert_eq!(2, restored.workspaces.len());
+        assert_eq!(state.workspaces[0].root, restored.workspaces[0].root);
+        assert_eq!(state.workspaces[1].root, restored.workspaces[1].root);
+        assert_eq!(Some(3), restored.workspaces[0].focus);
+        assert_eq!("2", restored.workspaces[1].name);
+        assert_eq!(1, restored.current_workspace);
+        assert_eq!(tile::Orientation::Vertical, restored.orientation);
+    }
+
+    #[test]
+    fn layout_should_round_trip_windows_outside_the_tree() {
+        let mut state = state();
+        let first = &mut state.workspaces[0];
+        let place = first.place(2).unwrap();
+        first.remove_window(2);
+        first.minimized.push((2, place.clone()));
+        let mut floating = tile::Node { node_type: tile::NodeType::Window(4), dim: first.root.dim.clone() };
+        floating.dim = tile::Dimensions { x: (100, 800), y: (100, 600) };
+        first.floating.push(floating.clone());
+        first.zoom = Some(Zoom { window: 3, area: ZoomArea::Monitor });
+        state.scratchpad.add(5, Some("notes".to_string()), false);
+
+        let json = to_json(&capture(&state, &info));
+        let restored: State<usize> = from_json(&json).unwrap().to_state(Box::new(FakeClock::default())).unwrap();
+
+        let first = &restored.workspaces[0];
+        assert_eq!(vec![floating], first.floating);
+        assert_eq!(vec![(2, place)], first.minimized);
+        assert_eq!(Some(Zoom { window: 3, area: ZoomArea::Monitor }), first.zoom);
+        assert_eq!(state.scratchpad.windows, restored.scratchpad.windows);
+    }
+
+    #[test]
+    fn capture_should_include_window_metadata() {
+        let layout = capture(&state(), &info);
+
+        if let LayoutNode::Container { children, .. } = &layout.workspaces[0].root {
+            if let LayoutNode::Window { id, title, process, .. } = &children[0] {
+                assert_eq!(1, *id);
+                assert_eq!("window 1", title);
+                assert_eq!("notepad.exe", process);
+            } else {
+                panic!("Left child is not a window");
+            }
+        } else {
+            panic!("Root is not a container");
+        }
+    }
+
+    #[test]
+    fn from_json_should_reject_newer_versions() {
+        let mut layout = capture(&state(), &info);
+        layout.version = VERSION + 1;
+
+        assert!(from_json(&to_json(&layout)).is_err());
+    }
+
+    #[test]
+    fn from_json_should_reject_missing_version() {
+        assert!(from_json("{\"workspaces\": []}").is_err());
+    }
+
+    #[test]
+    fn to_state_should_reject_containers_without_two_children() {
+        let rect = Rect { x: 0, y: 0, width: 100, height: 100 };
+        let mut workspace = Workspace::<usize>::new("1", (&rect).into());
+        let node = LayoutNode::Container {
+            orientation: tile::Orientation::Horizontal,
+            ratio: 0.5,
+            rect: rect.clone(),
+            children: vec![LayoutNode::Empty { rect }],
+        };
+
+        assert!(node.to_node(&mut workspace).is_err());
+    }
+}
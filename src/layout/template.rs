@@ -0,0 +1,206 @@
+use crate::internal::Criteria;
+use crate::tile;
+use crate::workspace::Workspace;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Templates are written by hand, so their format is versioned on its own and doesn't change
+// along with the layout tiles10 saves.
+pub const TEMPLATE_VERSION: u32 = 1;
+
+// A template describes a layout up front, with placeholders instead of windows:
+//
+//   {
+//     "version": 1,
+//     "root": {
+//       "type": "container", "orientation": "vertical", "ratio": 0.6,
+//       "children": [
+//         { "type": "placeholder", "match": { "process": "code.exe" } },
+//         { "type": "container", "orientation": "horizontal", "children": [
+//           { "type": "placeholder", "match": { "class": "CASCADIA_HOSTING_WINDOW_CLASS" } },
+//           { "type": "placeholder", "match": { "class": "CASCADIA_HOSTING_WINDOW_CLASS" } }
+//         ] }
+//       ]
+//     }
+//   }
+//
+// Rects are worked out from wherever the template is applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub version: u32,
+    pub root: TemplateNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TemplateNode {
+    Container {
+        orientation: tile::Orientation,
+        #[serde(default = "default_ratio")]
+        ratio: f32,
+        children: Vec<TemplateNode>,
+    },
+    Placeholder {
+        #[serde(rename = "match")]
+        criteria: Criteria,
+    },
+}
+
+fn default_ratio() -> f32 {
+    tile::DEFAULT_RATIO
+}
+
+pub fn load(path: &Path) -> Result<Template, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    parse(&contents)
+}
+
+pub fn parse(json: &str) -> Result<Template, String> {
+    let template: Template = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if template.version > TEMPLATE_VERSION {
+        return Err(format!(
+            "template version {} is newer than supported version {}",
+            template.version, TEMPLATE_VERSION
+        ));
+    }
+    validate(&template.root)?;
+    Ok(template)
+}
+
+fn validate(node: &TemplateNode) -> Result<(), String> {
+    if let TemplateNode::Container { ratio, children, .. } = node {
+        if children.len() != 2 {
+            return Err(format!("containers need exactly 2 children, found {}", children.len()));
+        }
+        if !(0.0..=1.0).contains(ratio) {
+            return Err(format!("container ratio {} is not between 0 and 1", ratio));
+        }
+        for child in children {
+            validate(child)?;
+        }
+    }
+    Ok(())
+}
+
+impl TemplateNode {
+    // Builds the template's tree, registering a slot on the workspace for every placeholder.
+    // The returned node has no size yet, it gets one when it's tiled into the workspace.
+    pub fn to_node<W>(&self, workspace: &mut Workspace<W>) -> tile::Node<W> {
+        let node_type = match self {
+            TemplateNode::Container { orientation, ratio, children } => tile::NodeType::Separator(
+                *orientation,
+                *ratio,
+                Box::new(children[0].to_node(workspace)),
+                Box::new(children[1].to_node(workspace)),
+            ),
+            TemplateNode::Placeholder { criteria } => {
+                tile::NodeType::Placeholder(workspace.add_slot(criteria.clone()))
+            }
+        };
+        tile::Node {
+            node_type,
+            dim: tile::Dimensions { x: (0, 0), y: (0, 0) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::internal::WindowInfo;
+
+    const TEMPLATE: &str = r#"{
+        "version": 1,
+        "root": {
+            "type": "container", "orientation": "vertical", "ratio": 0.6,
+            "children": [
+                { "type": "placeholder", "match": { "process": "code.exe" } },
+                { "type": "container", "orientation": "horizontal", "children": [
+                    { "type": "placeholder", "match": { "process": "cmd.exe" } },
+                    { "type": "placeholder", "match": { "process": "cmd.exe" } }
+                ] }
+            ]
+        }
+    }"#;
+
+    fn info(process: &str) -> WindowInfo {
+        WindowInfo {
+            process: process.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn dim() -> tile::Dimensions {
+        tile::Dimensions { x: (0, 1000), y: (0, 1000) }
+    }
+
+    #[test]
+    fn parse_should_reject_containers_without_two_children() {
+        let json = r#"{ "version": 1, "root": { "type": "container", "orientation": "vertical", "children": [] } }"#;
+
+        assert!(parse(json).is_err());
+    }
+
+    #[test]
+    fn parse_should_reject_newer_template_versions() {
+        let json = TEMPLATE.replacen("\"version\": 1", &format!("\"version\": {}", TEMPLATE_VERSION + 1), 1);
+
+        assert!(parse(&json).is_err());
+    }
+
+    #[test]
+    fn parse_should_default_ratio_to_half() {
+        let template = parse(TEMPLATE).unwrap();
+
+        if let TemplateNode::Container { children, .. } = &template.root {
+            if let TemplateNode::Container { ratio, .. } = &children[1] {
+                assert_eq!(tile::DEFAULT_RATIO, *ratio);
+                return;
+            }
+        }
+        panic!("Template does not have a nested container");
+    }
+
+    #[test]
+    fn applied_template_should_be_filled_by_matching_windows_in_order() {
+        let mut workspace: Workspace<usize> = Workspace::new("1", dim());
+        let node = parse(TEMPLATE).unwrap().root.to_node(&mut workspace);
+        tile::tile_node(&mut workspace.root, tile::Orientation::Vertical, node);
+
+        assert!(workspace.claim_placeholder(1, &info("cmd.exe")));
+        assert!(!workspace.claim_placeholder(2, &info("notepad.exe")));
+        assert!(workspace.claim_placeholder(3, &info("CODE.EXE")));
+        assert!(workspace.claim_placeholder(4, &info("cmd.exe")));
+        assert!(!workspace.claim_placeholder(5, &info("cmd.exe")));
+
+        if let tile::NodeType::Separator(tile::Orientation::Vertical, _, left, right) = &workspace.root.node_type {
+            assert_eq!(tile::NodeType::Window(3), left.node_type);
+            assert_eq!(tile::Dimensions { x: (0, 600), y: (0, 1000) }, left.dim);
+            if let tile::NodeType::Separator(_, _, top, bottom) = &right.node_type {
+                assert_eq!(tile::NodeType::Window(1), top.node_type);
+                assert_eq!(tile::NodeType::Window(4), bottom.node_type);
+                assert_eq!(tile::Dimensions { x: (601, 400), y: (501, 500) }, bottom.dim);
+            } else {
+                panic!("Right side is not a separator");
+            }
+        } else {
+            panic!("Root is not a vertical separator");
+        }
+        assert!(workspace.slots.is_empty());
+    }
+
+    #[test]
+    fn clear_placeholders_should_give_space_to_filled_slots() {
+        let mut workspace: Workspace<usize> = Workspace::new("1", dim());
+        let node = parse(TEMPLATE).unwrap().root.to_node(&mut workspace);
+        tile::tile_node(&mut workspace.root, tile::Orientation::Vertical, node);
+        workspace.claim_placeholder(1, &info("cmd.exe"));
+
+        workspace.clear_placeholders();
+
+        assert_eq!(tile::NodeType::Window(1), workspace.root.node_type);
+        assert_eq!(dim(), workspace.root.dim);
+        assert!(workspace.slots.is_empty());
+    }
+}
@@ -0,0 +1,9 @@
+pub mod command;
+pub mod config;
+pub mod core;
+pub mod tile;
+pub mod internal;
+pub mod layout;
+pub mod monitor;
+pub mod scratchpad;
+pub mod workspace;
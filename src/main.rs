@@ -1,14 +1,49 @@
-pub mod core;
-pub mod tile;
-pub mod internal;
+use std::path::Path;
+use tiles10::core;
+#[cfg(all(windows, feature = "windows"))]
+use tiles10::{config, internal};
 
 fn main() {
-    run();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("replay") => replay(args.get(1)),
+        _ => run(&args),
+    }
+}
+
+fn replay(path: Option<&String>) {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            println!("usage: tiles10 replay <file>");
+            return;
+        }
+    };
+
+    match core::replay::run(Path::new(path)) {
+        Ok(lines) => {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        Err(e) => println!("replay failed: {}", e),
+    }
 }
 
-#[cfg(windows)]
-fn run() {
-    let res = core::run();
+#[cfg(all(windows, feature = "windows"))]
+fn run(args: &[String]) {
+    let config = match load_config(args) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("invalid config: {}", e);
+            return;
+        }
+    };
+
+    let res = start_recorder(args).and_then(|recorder| {
+        core::run(internal::WinBackend::new(), &config, recorder, start_session(args), |e| println!("{}", e))
+    });
 
     match res {
         Ok(_) => println!("process exited successfully"),
@@ -16,7 +51,48 @@ fn run() {
     }
 }
 
+#[cfg(all(windows, feature = "windows"))]
+fn load_config(args: &[String]) -> Result<config::Config, String> {
+    let path = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => args.get(i + 1).map(std::path::PathBuf::from),
+        None => config::default_path(),
+    };
+    match path {
+        Some(path) => config::Config::load(&path),
+        None => Ok(config::Config::default()),
+    }
+}
+
+#[cfg(all(windows, feature = "windows"))]
+fn start_recorder(args: &[String]) -> Result<Option<core::record::Recorder>, std::io::Error> {
+    match args.iter().position(|arg| arg == "--record") {
+        Some(i) => match args.get(i + 1) {
+            Some(path) => core::record::Recorder::create(Path::new(path)).map(Some),
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "--record needs a file")),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(all(windows, feature = "windows"))]
+fn start_session(args: &[String]) -> Option<core::session::Session> {
+    if args.iter().any(|arg| arg == "--no-session") {
+        return None;
+    }
+
+    let path = match args.iter().position(|arg| arg == "--session") {
+        Some(i) => args.get(i + 1).map(std::path::PathBuf::from),
+        None => core::session::default_path(),
+    };
+    path.map(core::session::Session::new)
+}
+
 #[cfg(not(windows))]
-fn run() {
+fn run(_args: &[String]) {
     println!("Please run this application on a desktop with Windows 10")
 }
+
+#[cfg(all(windows, not(feature = "windows")))]
+fn run(_args: &[String]) {
+    println!("This build has no backend, rebuild with the windows feature enabled")
+}
@@ -0,0 +1,238 @@
+use crate::tile;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    // the same for as long as the monitor is connected, and again when it comes back
+    pub id: String,
+    // the whole monitor
+    pub area: tile::Dimensions,
+    // what the taskbar leaves of it
+    pub work_area: tile::Dimensions,
+    // physical pixels per logical pixel, 1.0 at 96 DPI
+    pub scale: f32,
+}
+
+// Logical pixels, as written in the config, in physical pixels on a monitor with this scale.
+pub fn scaled(value: i32, scale: f32) -> i32 {
+    (value as f32 * scale).round() as i32
+}
+
+// Space kept free along each edge of a monitor, for bars and docks that don't reserve it with
+// the system themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Edges {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+// A monitor's reserved edges, from the config. The monitor is given by its id, or by its number
+// (from 1, primary first) among the monitors connected at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reservation {
+    pub monitor: String,
+    pub edges: Edges,
+}
+
+// Turns monitor numbers into the ids of the monitors they stand for, so that reservations stay
+// with their monitor when others are connected or disconnected later on. Numbers without a
+// monitor are kept as they are.
+pub fn resolve(reservations: &[Reservation], monitors: &[Monitor]) -> Vec<Reservation> {
+    reservations
+        .iter()
+        .map(|reservation| {
+            let number = reservation.monitor.parse::<usize>().ok();
+            match number.and_then(|number| monitors.get(number.wrapping_sub(1))) {
+                Some(monitor) => Reservation { monitor: monitor.id.clone(), edges: reservation.edges },
+                None => reservation.clone(),
+            }
+        })
+        .collect()
+}
+
+// The part of area left for tiling once the reserved edges are taken off.
+pub fn work_area(area: &tile::Dimensions, reserved: &Edges) -> tile::Dimensions {
+    tile::Dimensions {
+        x: (area.x.0 + reserved.left, (area.x.1 - reserved.left - reserved.right).max(0)),
+        y: (area.y.0 + reserved.top, (area.y.1 - reserved.top - reserved.bottom).max(0)),
+    }
+}
+
+// Undoes work_area, giving back the area the edges were taken off.
+pub fn grow(area: &tile::Dimensions, edges: &Edges) -> tile::Dimensions {
+    tile::Dimensions {
+        x: (area.x.0 - edges.left, area.x.1 + edges.left + edges.right),
+        y: (area.y.0 - edges.top, area.y.1 + edges.top + edges.bottom),
+    }
+}
+
+// How far each side of a window in area is pulled in to leave gap pixels around it: all of the
+// gap along the edges of the area, half of it next to another window, which gives the other
+// half.
+pub fn gap_insets(dim: &tile::Dimensions, area: &tile::Dimensions, gap: i32) -> Edges {
+    let (near, far) = (gap / 2, gap - gap / 2);
+    Edges {
+        left: if dim.x.0 <= area.x.0 { gap } else { near },
+        right: if dim.x.0 + dim.x.1 >= area.x.0 + area.x.1 { gap } else { far },
+        top: if dim.y.0 <= area.y.0 { gap } else { near },
+        bottom: if dim.y.0 + dim.y.1 >= area.y.0 + area.y.1 { gap } else { far },
+    }
+}
+
+// How much bigger a window's rect is than the frame that's actually drawn on each side, the
+// invisible resize borders windows 10 puts around most windows. Growing the rect a window should
+// be shown in by these gives the rect to ask for so that its visible frame fills it exactly.
+pub fn frame_insets(window: &tile::Dimensions, frame: &tile::Dimensions) -> Edges {
+    Edges {
+        left: (frame.x.0 - window.x.0).max(0),
+        right: ((window.x.0 + window.x.1) - (frame.x.0 + frame.x.1)).max(0),
+        top: (frame.y.0 - window.y.0).max(0),
+        bottom: ((window.y.0 + window.y.1) - (frame.y.0 + frame.y.1)).max(0),
+    }
+}
+
+// The edges reserved on the monitor with the given id, nothing if the config doesn't mention it.
+pub fn reserved(reservations: &[Reservation], monitor: &str) -> Edges {
+    reservations
+        .iter()
+        .find(|reservation| reservation.monitor == monitor)
+        .map(|reservation| reservation.edges)
+        .unwrap_or_default()
+}
+
+// Which monitor each workspace goes on, given the id of the monitor it belongs to: that one
+// while it's connected, the primary (the first) otherwise. Returns an index into monitors for
+// every workspace.
+pub fn place(homes: &[Option<String>], monitors: &[Monitor]) -> Vec<usize> {
+    homes
+        .iter()
+        .map(|home| {
+            monitors
+                .iter()
+                .position(|monitor| Some(&monitor.id) == home.as_ref())
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+// Moves and scales dim, which was somewhere in from, to the same relative place in to.
+pub fn rescale(dim: &tile::Dimensions, from: &tile::Dimensions, to: &tile::Dimensions) -> tile::Dimensions {
+    let scale = |(pos, len): (i32, i32), (from_pos, from_len): (i32, i32), (to_pos, to_len): (i32, i32)| {
+        if from_len <= 0 {
+            return (to_pos, len);
+        }
+        let scaled = |value: i32| (value as i64 * to_len as i64 / from_len as i64) as i32;
+        (to_pos + scaled(pos - from_pos), scaled(len))
+    };
+    tile::Dimensions {
+        x: scale(dim.x, from.x, to.x),
+        y: scale(dim.y, from.y, to.y),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn monitor(id: &str, x: i32) -> Monitor {
+        let area = tile::Dimensions { x: (x, 1920), y: (0, 1080) };
+        Monitor { id: id.to_string(), work_area: area.clone(), area, scale: 1.0 }
+    }
+
+    #[test]
+    fn work_area_should_take_reserved_edges_off() {
+        let area = tile::Dimensions { x: (0, 1920), y: (0, 1040) };
+        let reserved = Edges { top: 30, bottom: 0, left: 60, right: 10 };
+
+        assert_eq!(tile::Dimensions { x: (60, 1850), y: (30, 1010) }, work_area(&area, &reserved));
+        assert_eq!(area, work_area(&area, &Edges::default()));
+    }
+
+    #[test]
+    fn frame_insets_should_measure_invisible_borders() {
+        // a typical windows 10 window: 7px borders on the sides and bottom, none on top
+        let window = tile::Dimensions { x: (93, 974), y: (0, 1087) };
+        let frame = tile::Dimensions { x: (100, 960), y: (0, 1080) };
+
+        let insets = frame_insets(&window, &frame);
+
+        assert_eq!(Edges { top: 0, bottom: 7, left: 7, right: 7 }, insets);
+        let tile = tile::Dimensions { x: (961, 959), y: (0, 1080) };
+        assert_eq!(tile::Dimensions { x: (954, 973), y: (0, 1087) }, grow(&tile, &insets));
+    }
+
+    #[test]
+    fn frame_insets_should_ignore_frame_drawn_outside_window() {
+        let window = tile::Dimensions { x: (0, 800), y: (0, 600) };
+        let frame = tile::Dimensions { x: (-2, 804), y: (1, 598) };
+
+        assert_eq!(Edges { top: 1, bottom: 1, left: 0, right: 0 }, frame_insets(&window, &frame));
+        assert_eq!(Edges::default(), frame_insets(&window, &window));
+    }
+
+    #[test]
+    fn place_should_move_workspaces_off_removed_monitor_and_back() {
+        let homes = vec![Some("a".to_string()), Some("b".to_string()), Some("b".to_string()), None];
+        let both = vec![monitor("a", 0), monitor("b", 1920)];
+        let only_b = vec![monitor("b", 0)];
+        let only_c = vec![monitor("c", 0)];
+
+        assert_eq!(vec![0, 1, 1, 0], place(&homes, &both));
+        assert_eq!(vec![0, 0, 0, 0], place(&homes, &only_b));
+        assert_eq!(vec![0, 0, 0, 0], place(&homes, &only_c));
+        // both came back, behind a new primary
+        let all = vec![monitor("c", 0), monitor("b", 1920), monitor("a", 3840)];
+        assert_eq!(vec![2, 1, 1, 0], place(&homes, &all));
+    }
+
+    #[test]
+    fn rescale_should_keep_relative_position_and_size() {
+        let from = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+        let to = tile::Dimensions { x: (1920, 3840), y: (0, 2160) };
+        let dim = tile::Dimensions { x: (480, 960), y: (270, 540) };
+
+        assert_eq!(tile::Dimensions { x: (2880, 1920), y: (540, 1080) }, rescale(&dim, &from, &to));
+        assert_eq!(dim, rescale(&dim, &from, &from));
+    }
+
+    #[test]
+    fn gap_insets_should_split_gap_between_neighbours() {
+        let area = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+        let left = tile::Dimensions { x: (0, 960), y: (0, 1080) };
+        let right = tile::Dimensions { x: (961, 960), y: (0, 1080) };
+
+        assert_eq!(Edges { top: 9, bottom: 9, left: 9, right: 5 }, gap_insets(&left, &area, 9));
+        assert_eq!(Edges { top: 9, bottom: 9, left: 4, right: 9 }, gap_insets(&right, &area, 9));
+        assert_eq!(right, grow(&work_area(&right, &gap_insets(&right, &area, 9)), &gap_insets(&right, &area, 9)));
+    }
+
+    #[test]
+    fn scaled_should_round_to_physical_pixels() {
+        assert_eq!(10, scaled(10, 1.0));
+        assert_eq!(15, scaled(10, 1.5));
+        assert_eq!(13, scaled(10, 1.25));
+    }
+
+    #[test]
+    fn reserved_should_find_edges_of_monitor() {
+        let edges = Edges { top: 30, ..Default::default() };
+        let reservations = vec![Reservation { monitor: "DISPLAY2".to_string(), edges }];
+
+        assert_eq!(edges, reserved(&reservations, "DISPLAY2"));
+        assert_eq!(Edges::default(), reserved(&reservations, "DISPLAY1"));
+    }
+
+    #[test]
+    fn resolve_should_turn_numbers_into_ids_of_monitors() {
+        let reservation = |monitor: &str, top: i32| Reservation {
+            monitor: monitor.to_string(),
+            edges: Edges { top, ..Default::default() },
+        };
+        let monitors = vec![monitor("DISPLAY2", 0), monitor("DISPLAY1", 1920)];
+
+        let resolved = resolve(&[reservation("1", 30), reservation("DISPLAY1", 20), reservation("3", 10)], &monitors);
+
+        assert_eq!(vec![reservation("DISPLAY2", 30), reservation("DISPLAY1", 20), reservation("3", 10)], resolved);
+    }
+}
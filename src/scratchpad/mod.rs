@@ -0,0 +1,219 @@
+use crate::internal::{Criteria, WindowInfo};
+use crate::tile;
+
+// Windows taken out of the tiling tree and kept hidden until they are summoned. A summoned
+// window floats centered over whatever workspace is current.
+pub struct Scratchpad<W> {
+    pub windows: Vec<ScratchWindow<W>>,
+    pub named: Vec<NamedScratchpad>,
+    // named scratchpads whose program was launched but whose window hasn't shown up yet
+    pending: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScratchWindow<W> {
+    pub window: W,
+    pub name: Option<String>,
+    pub visible: bool,
+}
+
+// A scratchpad that starts its own program the first time it's summoned. The window it opens is
+// recognized by the criteria.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedScratchpad {
+    pub name: String,
+    pub criteria: Criteria,
+    pub program: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action<W> {
+    Show(W),
+    Hide(W),
+    Launch(String),
+    Nothing,
+}
+
+impl<W: Copy + PartialEq> Scratchpad<W> {
+    pub fn new(named: Vec<NamedScratchpad>) -> Scratchpad<W> {
+        Scratchpad {
+            windows: Vec::new(),
+            named,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, window: W, name: Option<String>, visible: bool) {
+        self.windows.push(ScratchWindow { window, name, visible });
+    }
+
+    pub fn remove(&mut self, window: W) -> bool {
+        let len = self.windows.len();
+        self.windows.retain(|scratch| scratch.window != window);
+        self.windows.len() != len
+    }
+
+    // Without a name, hides the window that is showing, or shows the one added last. With a
+    // name, toggles that scratchpad's window, launching its program if it has none yet.
+    pub fn toggle(&mut self, name: Option<&str>) -> Action<W> {
+        let found = match name {
+            Some(name) => self.windows.iter().position(|scratch| scratch.name.as_deref() == Some(name)),
+            None => self
+                .windows
+                .iter()
+                .position(|scratch| scratch.visible)
+                .or_else(|| self.windows.len().checked_sub(1)),
+        };
+
+        match (found, name) {
+            (Some(i), _) => {
+                let scratch = &mut self.windows[i];
+                scratch.visible = !scratch.visible;
+                if scratch.visible {
+                    Action::Show(scratch.window)
+                } else {
+                    Action::Hide(scratch.window)
+                }
+            }
+            (None, Some(name)) => match self.named.iter().find(|named| named.name == name) {
+                Some(named) if !self.pending.contains(&named.name) => {
+                    self.pending.push(named.name.clone());
+                    Action::Launch(named.program.clone())
+                }
+                _ => Action::Nothing,
+            },
+            (None, None) => Action::Nothing,
+        }
+    }
+
+    // The program couldn't be started, so no window is coming. Summoning the scratchpad again
+    // tries another launch.
+    pub fn launch_failed(&mut self, name: &str) {
+        self.pending.retain(|pending| pending != name);
+    }
+
+    // Takes a new window for the launched scratchpad it belongs to. The window is shown, since
+    // it was summoned.
+    pub fn claim(&mut self, window: W, info: &WindowInfo) -> bool {
+        let named = &self.named;
+        let found = self.pending.iter().position(|name| {
+            named
+                .iter()
+                .any(|named| &named.name == name && named.criteria.matches(info))
+        });
+
+        match found {
+            Some(i) => {
+                let name = self.pending.remove(i);
+                self.add(window, Some(name), true);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Splits a command line into words at whitespace, except inside double quotes, which are taken
+// out, so `"C:\Program Files\app.exe" --flag` is two words. Each word comes with the byte it
+// starts at in the line.
+pub fn split_words(line: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut word: Option<(usize, String)> = None;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        if c.is_whitespace() && !quoted {
+            words.extend(word.take());
+            continue;
+        }
+        let (_, text) = word.get_or_insert_with(|| (i, String::new()));
+        if c == '"' {
+            quoted = !quoted;
+        } else {
+            text.push(c);
+        }
+    }
+    words.extend(word);
+    words
+}
+
+// Scratchpad windows float centered over the workspace at half its size.
+pub fn float_dimensions(area: &tile::Dimensions) -> tile::Dimensions {
+    tile::Dimensions {
+        x: (area.x.0 + area.x.1 / 4, area.x.1 / 2),
+        y: (area.y.0 + area.y.1 / 4, area.y.1 / 2),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn terminal() -> NamedScratchpad {
+        NamedScratchpad {
+            name: "term".to_string(),
+            criteria: Criteria {
+                process: Some("wt.exe".to_string()),
+                ..Default::default()
+            },
+            program: "wt.exe".to_string(),
+        }
+    }
+
+    #[test]
+    fn toggle_should_hide_visible_window_before_showing_another() {
+        let mut scratchpad: Scratchpad<i32> = Scratchpad::new(Vec::new());
+        scratchpad.add(1, None, false);
+        scratchpad.add(2, None, false);
+
+        assert_eq!(Action::Show(2), scratchpad.toggle(None));
+        assert_eq!(Action::Hide(2), scratchpad.toggle(None));
+        assert!(scratchpad.remove(2));
+        assert_eq!(Action::Show(1), scratchpad.toggle(None));
+    }
+
+    #[test]
+    fn toggle_named_should_launch_program_once_and_claim_its_window() {
+        let mut scratchpad: Scratchpad<i32> = Scratchpad::new(vec![terminal()]);
+        let info = WindowInfo {
+            process: "WT.exe".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(Action::Launch("wt.exe".to_string()), scratchpad.toggle(Some("term")));
+        assert_eq!(Action::Nothing, scratchpad.toggle(Some("term")));
+        assert!(!scratchpad.claim(3, &WindowInfo::default()));
+        assert!(scratchpad.claim(4, &info));
+        assert_eq!(Action::Hide(4), scratchpad.toggle(Some("term")));
+        assert_eq!(Action::Nothing, scratchpad.toggle(Some("music")));
+    }
+
+    #[test]
+    fn launch_failed_should_let_named_scratchpad_launch_again() {
+        let mut scratchpad: Scratchpad<i32> = Scratchpad::new(vec![terminal()]);
+
+        assert_eq!(Action::Launch("wt.exe".to_string()), scratchpad.toggle(Some("term")));
+        scratchpad.launch_failed("term");
+        assert_eq!(Action::Launch("wt.exe".to_string()), scratchpad.toggle(Some("term")));
+    }
+
+    #[test]
+    fn split_words_should_keep_quoted_spaces() {
+        let words = split_words(r#" "C:\Program Files\app.exe"  --flag title="My App" "#);
+
+        assert_eq!(
+            vec![
+                (1, r"C:\Program Files\app.exe".to_string()),
+                (29, "--flag".to_string()),
+                (36, "title=My App".to_string()),
+            ],
+            words
+        );
+    }
+
+    #[test]
+    fn float_dimensions_should_center_window() {
+        let area = tile::Dimensions { x: (0, 1920), y: (0, 1080) };
+
+        assert_eq!(tile::Dimensions { x: (480, 960), y: (270, 540) }, float_dimensions(&area));
+    }
+}
@@ -1,18 +1,215 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Orientation {
     Horizontal,
     Vertical,
 }
 
-#[derive(Debug, Clone)]
+impl Orientation {
+    pub fn toggle(&self) -> Orientation {
+        match self {
+            Orientation::Horizontal => Orientation::Vertical,
+            Orientation::Vertical => Orientation::Horizontal,
+        }
+    }
+}
+
+// side of a node, used to say where a new node goes relative to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub fn orientation(&self) -> Orientation {
+        match self {
+            Direction::Left | Direction::Right => Orientation::Vertical,
+            Direction::Up | Direction::Down => Orientation::Horizontal,
+        }
+    }
+
+    // the side the first child of a separator with this orientation is on
+    pub fn before(orientation: Orientation) -> Direction {
+        match orientation {
+            Orientation::Horizontal => Direction::Up,
+            Orientation::Vertical => Direction::Left,
+        }
+    }
+
+    // the side new nodes go to by default
+    pub fn after(orientation: Orientation) -> Direction {
+        match orientation {
+            Orientation::Horizontal => Direction::Down,
+            Orientation::Vertical => Direction::Right,
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s {
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            _ => Err(format!("unknown direction '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Left => write!(f, "left"),
+            Direction::Right => write!(f, "right"),
+            Direction::Up => write!(f, "up"),
+            Direction::Down => write!(f, "down"),
+        }
+    }
+}
+
+// clockwise turn applied to a subtree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rotation, String> {
+        match s {
+            "90" => Ok(Rotation::Quarter),
+            "180" => Ok(Rotation::Half),
+            "270" => Ok(Rotation::ThreeQuarters),
+            _ => Err(format!("can only rotate by 90, 180 or 270 degrees, not '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rotation::Quarter => write!(f, "90"),
+            Rotation::Half => write!(f, "180"),
+            Rotation::ThreeQuarters => write!(f, "270"),
+        }
+    }
+}
+
+// where new windows go when nothing is preselected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Insertion {
+    // split the focused window in the current orientation
+    SplitFocused,
+    // add to the end of the focused window's container
+    Parent,
+    // add to the end of the whole tree
+    Root,
+    // split the focused window along its longer side
+    Auto,
+}
+
+impl FromStr for Insertion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Insertion, String> {
+        match s {
+            "split" => Ok(Insertion::SplitFocused),
+            "parent" => Ok(Insertion::Parent),
+            "root" => Ok(Insertion::Root),
+            "auto" => Ok(Insertion::Auto),
+            _ => Err(format!("unknown insertion policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Insertion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Insertion::SplitFocused => write!(f, "split"),
+            Insertion::Parent => write!(f, "parent"),
+            Insertion::Root => write!(f, "root"),
+            Insertion::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+// What happens when the windows' minimum sizes add up to more room than there is. Either way the
+// other windows are shrunk first, down to their own minimums.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    // squeeze the windows below their minimums, in proportion to them, so they overlap
+    Shrink,
+    // float the windows that don't fit until the rest do
+    Float,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Overflow, String> {
+        match s {
+            "shrink" => Ok(Overflow::Shrink),
+            "float" => Ok(Overflow::Float),
+            _ => Err(format!("unknown overflow policy '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Overflow::Shrink => write!(f, "shrink"),
+            Overflow::Float => write!(f, "float"),
+        }
+    }
+}
+
+// Part of a window a dragged window is dropped on: the middle swaps the two, an edge puts the
+// dragged window next to the target on that side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropZone {
+    Center,
+    Edge(Direction),
+}
+
+// Where a window taken out of the tree was: its path, and the split it was one side of, with
+// the share of the split it had.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    pub path: Vec<usize>,
+    pub orientation: Orientation,
+    pub ratio: f32,
+}
+
+// share of the separator's space given to the left (or top) child
+pub const DEFAULT_RATIO: f32 = 0.5;
+// smallest share resizing leaves either side of a separator
+pub const MIN_RATIO: f32 = 0.05;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NodeType<T> {
-    Separator(Orientation, Box<Node<T>>, Box<Node<T>>),
+    Separator(Orientation, f32, Box<Node<T>>, Box<Node<T>>),
     Empty,
     Window(T),
+    // reserved space waiting for a matching window, the value identifies the slot
+    Placeholder(usize),
 }
 
 // TODO: Arbitrary number of children?
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Node<T> {
     pub node_type: NodeType<T>,
     pub dim: Dimensions,
@@ -24,35 +221,65 @@ pub struct Dimensions {
     pub y: (i32, i32),
 }
 
-pub fn tile_vertical(dim: &Dimensions) -> (Dimensions, Dimensions) {
+// Smallest and largest size a window can be given, as (width, height).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeHints {
+    pub min: (i32, i32),
+    pub max: (i32, i32),
+}
+
+impl Default for SizeHints {
+    fn default() -> SizeHints {
+        SizeHints { min: (0, 0), max: (i32::MAX, i32::MAX) }
+    }
+}
+
+impl SizeHints {
+    // (min, max) along the axis a separator of this orientation splits
+    fn along(&self, orientation: Orientation) -> (i32, i32) {
+        match orientation {
+            Orientation::Vertical => (self.min.0, self.max.0),
+            Orientation::Horizontal => (self.min.1, self.max.1),
+        }
+    }
+}
+
+fn split_length(length: i32, ratio: f32) -> i32 {
+    (length as f32 * ratio).round() as i32
+}
+
+pub fn tile_vertical(dim: &Dimensions, ratio: f32) -> (Dimensions, Dimensions) {
+    let left_width = split_length(dim.x.1, ratio);
     let left_dim = Dimensions {
-        x: (dim.x.0, dim.x.1 / 2),
+        x: (dim.x.0, left_width),
         y: (dim.y.0, dim.y.1),
     };
     let right_dim = Dimensions {
-        x: (dim.x.0 + dim.x.1 / 2 + 1, dim.x.1 / 2),
+        x: (dim.x.0 + left_width + 1, dim.x.1 - left_width),
         y: (dim.y.0, dim.y.1),
     };
     (left_dim, right_dim)
 }
 
-pub fn tile_horizontal(dim: &Dimensions) -> (Dimensions, Dimensions) {
+pub fn tile_horizontal(dim: &Dimensions, ratio: f32) -> (Dimensions, Dimensions) {
+    let top_height = split_length(dim.y.1, ratio);
     let top_dim = Dimensions {
         x: (dim.x.0, dim.x.1),
-        y: (dim.y.0, dim.y.1 / 2),
+        y: (dim.y.0, top_height),
     };
     let bottom_dim = Dimensions {
         x: (dim.x.0, dim.x.1),
-        y: (dim.y.0 + dim.y.1 / 2 + 1, dim.y.1 / 2),
+        y: (dim.y.0 + top_height + 1, dim.y.1 - top_height),
     };
     (top_dim, bottom_dim)
 }
 
-pub fn untile<T: Copy + PartialEq>(mut root: &mut Node<T>, window_val: &T) {
+pub fn untile<T: Copy + PartialEq>(root: &mut Node<T>, window_val: &T) {
     match &mut root.node_type {
-        NodeType::Window(_) => return,
-        NodeType::Empty => return, // for now, assume Empty cannot have children
-        NodeType::Separator(_, left_child, right_child) => {
+        NodeType::Window(_) => (),
+        NodeType::Placeholder(_) => (),
+        NodeType::Empty => (), // for now, assume Empty cannot have children
+        NodeType::Separator(_, _, left_child, right_child) => {
             if let NodeType::Window(child) = &left_child.node_type {
                 if child == window_val {
                     // left child is deleted window
@@ -71,57 +298,601 @@ pub fn untile<T: Copy + PartialEq>(mut root: &mut Node<T>, window_val: &T) {
                 }
             }
 
-            untile(left_child, &window_val);
-            untile(right_child, &window_val);
+            untile(left_child, window_val);
+            untile(right_child, window_val);
         }
     }
 }
 
 pub fn tile<T: Clone>(root: &mut Node<T>, orientation: Orientation, new_window: T) {
+    let new_win = Node {
+        node_type: NodeType::Window(new_window),
+        dim: root.dim.clone(),
+    };
+    tile_node(root, orientation, new_win);
+}
+
+pub fn tile_node<T: Clone>(root: &mut Node<T>, orientation: Orientation, new_node: Node<T>) {
+    split(root, Direction::after(orientation), DEFAULT_RATIO, new_node);
+}
+
+// Splits root in two, putting new_node on the given side of what was there. ratio is the share
+// of the space new_node gets.
+pub fn split<T: Clone>(root: &mut Node<T>, direction: Direction, ratio: f32, new_node: Node<T>) {
     if let NodeType::Empty = root.node_type {
-        root.node_type = NodeType::Window(new_window);
+        root.node_type = new_node.node_type;
+        resize_children(root);
         return;
     }
 
-    let (left_dim, right_dim) = match &orientation {
-        Orientation::Horizontal => tile_horizontal(&root.dim),
-        Orientation::Vertical => tile_vertical(&root.dim),
+    let existing = Node {
+        node_type: std::mem::replace(&mut root.node_type, NodeType::Empty),
+        dim: root.dim.clone(),
+    };
+    let (first, second, ratio) = match direction {
+        Direction::Left | Direction::Up => (new_node, existing, ratio),
+        Direction::Right | Direction::Down => (existing, new_node, 1.0 - ratio),
     };
 
-    // clone current and set it to left
-    let mut tmp = root.clone();
-    tmp.dim = left_dim;
+    root.node_type = NodeType::Separator(direction.orientation(), ratio, Box::new(first), Box::new(second));
+    resize_children(root);
+}
 
-    let new_win = Node {
-        node_type: NodeType::Window(new_window),
-        dim: right_dim,
-    };
+// Adds new_node to the tree according to the insertion policy. target is the node the policy is
+// applied to, usually the focused window.
+pub fn insert<T: Clone + PartialEq>(
+    root: &mut Node<T>,
+    target: Option<T>,
+    insertion: Insertion,
+    orientation: Orientation,
+    new_node: Node<T>,
+) {
+    match insertion {
+        Insertion::SplitFocused => match target.and_then(|target| find_node(root, target)) {
+            Some(node) => tile_node(node, orientation, new_node),
+            None => tile_node(root, orientation, new_node),
+        },
+        Insertion::Auto => match target.and_then(|target| find_node(root, target)) {
+            Some(node) => tile_node(node, longer_axis(&node.dim), new_node),
+            None => tile_node(root, longer_axis(&root.dim), new_node),
+        },
+        Insertion::Parent => match target.and_then(|target| find_parent(root, target)) {
+            Some(parent) => {
+                let orientation = match &parent.node_type {
+                    NodeType::Separator(orientation, ..) => *orientation,
+                    _ => orientation,
+                };
+                append(parent, orientation, new_node);
+            }
+            None => append(root, orientation, new_node),
+        },
+        Insertion::Root => append(root, orientation, new_node),
+    }
+}
+
+// Adds new_node after the children of root that are laid out along orientation, with the same
+// share of the space each of them would have if they were evenly spread.
+fn append<T: Clone>(root: &mut Node<T>, orientation: Orientation, new_node: Node<T>) {
+    let count = children_along(root, orientation);
+    split(root, Direction::after(orientation), 1.0 / (count + 1) as f32, new_node);
+}
+
+// Number of nodes side by side along orientation, counting nested separators of the same
+// orientation as one container.
+pub fn children_along<T>(root: &Node<T>, orientation: Orientation) -> usize {
+    match &root.node_type {
+        NodeType::Separator(o, _, left, right) if *o == orientation => {
+            children_along(left, orientation) + children_along(right, orientation)
+        }
+        _ => 1,
+    }
+}
 
-    root.node_type = NodeType::Separator(orientation, Box::new(tmp), Box::new(new_win));
-    if let NodeType::Separator(_, left_child, right_child) = &mut root.node_type {
-        resize_children(left_child);
-        resize_children(right_child);
+fn longer_axis(dim: &Dimensions) -> Orientation {
+    if dim.x.1 > dim.y.1 {
+        Orientation::Vertical
+    } else {
+        Orientation::Horizontal
     }
 }
 
-fn resize_children<T: std::clone::Clone>(root: &mut Node<T>) {
+pub fn resize_children<T: std::clone::Clone>(root: &mut Node<T>) {
     // TODO can do let (left,right) = match root.node_type ...
     match &mut root.node_type {
-        NodeType::Separator(Orientation::Horizontal, left_child, right_child) => {
-            let (left, right) = tile_horizontal(&root.dim);
+        NodeType::Separator(Orientation::Horizontal, ratio, left_child, right_child) => {
+            let (left, right) = tile_horizontal(&root.dim, *ratio);
             left_child.dim = left;
             right_child.dim = right;
             resize_children::<T>(left_child);
             resize_children::<T>(right_child);
         }
-        NodeType::Separator(Orientation::Vertical, left_child, right_child) => {
-            let (left, right) = tile_vertical(&root.dim);
+        NodeType::Separator(Orientation::Vertical, ratio, left_child, right_child) => {
+            let (left, right) = tile_vertical(&root.dim, *ratio);
             left_child.dim = left;
             right_child.dim = right;
             resize_children::<T>(left_child);
             resize_children::<T>(right_child);
         }
-        _ => return,
+        _ => (),
+    }
+}
+
+// Lays the tree out like resize_children, but moves separators so that windows get at least
+// their minimum and at most their maximum size wherever there's room for that. Returns the
+// windows that still end up smaller than their minimum.
+pub fn fit<T: Copy>(root: &mut Node<T>, hints: &dyn Fn(&T) -> SizeHints) -> Vec<T> {
+    let mut overflowing = Vec::new();
+    fit_children(root, hints, &mut overflowing);
+    overflowing
+}
+
+fn fit_children<T: Copy>(root: &mut Node<T>, hints: &dyn Fn(&T) -> SizeHints, overflowing: &mut Vec<T>) {
+    match &mut root.node_type {
+        NodeType::Separator(orientation, ratio, left_child, right_child) => {
+            let left = hints_of(left_child, hints).along(*orientation);
+            let right = hints_of(right_child, hints).along(*orientation);
+            let (left_dim, right_dim) = match orientation {
+                Orientation::Vertical => {
+                    let first = split_position(root.dim.x.1, *ratio, left, right);
+                    tile_vertical(&root.dim, first as f32 / root.dim.x.1.max(1) as f32)
+                }
+                Orientation::Horizontal => {
+                    let first = split_position(root.dim.y.1, *ratio, left, right);
+                    tile_horizontal(&root.dim, first as f32 / root.dim.y.1.max(1) as f32)
+                }
+            };
+            left_child.dim = left_dim;
+            right_child.dim = right_dim;
+            fit_children(left_child, hints, overflowing);
+            fit_children(right_child, hints, overflowing);
+        }
+        NodeType::Window(window) => {
+            let min = hints(window).min;
+            if root.dim.x.1 < min.0 || root.dim.y.1 < min.1 {
+                overflowing.push(*window);
+            }
+        }
+        NodeType::Placeholder(_) | NodeType::Empty => (),
+    }
+}
+
+// The sizes a whole subtree can take: side by side, the widths add up and the tallest minimum
+// wins, and the other way around for stacked windows.
+fn hints_of<T>(root: &Node<T>, hints: &dyn Fn(&T) -> SizeHints) -> SizeHints {
+    match &root.node_type {
+        NodeType::Separator(orientation, _, left, right) => {
+            let (left, right) = (hints_of(left, hints), hints_of(right, hints));
+            let sum = |a: i32, b: i32| a.saturating_add(b);
+            match orientation {
+                Orientation::Vertical => SizeHints {
+                    min: (sum(left.min.0, right.min.0), left.min.1.max(right.min.1)),
+                    max: (sum(left.max.0, right.max.0), left.max.1.max(right.max.1)),
+                },
+                Orientation::Horizontal => SizeHints {
+                    min: (left.min.0.max(right.min.0), sum(left.min.1, right.min.1)),
+                    max: (left.max.0.max(right.max.0), sum(left.max.1, right.max.1)),
+                },
+            }
+        }
+        NodeType::Window(window) => hints(window),
+        NodeType::Placeholder(_) | NodeType::Empty => SizeHints::default(),
+    }
+}
+
+// Length of the first side when splitting length by ratio, given the (min, max) of each side.
+// The ratio is kept as far as the limits allow. Minimums win over maximums, and when there's no
+// room for both minimums each side gets a share in proportion to its minimum.
+fn split_position(length: i32, ratio: f32, (left_min, left_max): (i32, i32), (right_min, right_max): (i32, i32)) -> i32 {
+    let wanted = split_length(length, ratio);
+    let low = left_min.max(length.saturating_sub(right_max));
+    let high = left_max.min(length - right_min);
+    if low <= high {
+        return wanted.clamp(low, high);
+    }
+    if left_min <= length - right_min {
+        return wanted.clamp(left_min, length - right_min);
+    }
+    (length as i64 * left_min as i64 / (left_min as i64 + right_min as i64)) as i32
+}
+
+// The windows in new that aren't in old with the same dimensions, with their new dimensions.
+pub fn changed<T: Copy + PartialEq>(old: &[(T, Dimensions)], new: &[(T, Dimensions)]) -> Vec<(T, Dimensions)> {
+    new.iter()
+        .filter(|(window, dim)| !old.iter().any(|(w, d)| w == window && d == dim))
+        .cloned()
+        .collect()
+}
+
+// Flips a separator between side by side and stacked, keeping its children and ratio.
+pub fn toggle_orientation<T: Clone>(root: &mut Node<T>) {
+    if let NodeType::Separator(orientation, ..) = &mut root.node_type {
+        *orientation = orientation.toggle();
+    }
+    resize_children(root);
+}
+
+pub fn rotate<T: Clone>(root: &mut Node<T>, rotation: Rotation) {
+    turn(root, rotation);
+    resize_children(root);
+}
+
+fn turn<T>(root: &mut Node<T>, rotation: Rotation) {
+    if let NodeType::Separator(orientation, ratio, left, right) = &mut root.node_type {
+        // turning clockwise, left goes to the top and the top goes to the right, so stacked
+        // children swap places when they end up side by side
+        let swap = match (rotation, *orientation) {
+            (Rotation::Quarter, Orientation::Horizontal) => true,
+            (Rotation::Quarter, Orientation::Vertical) => false,
+            (Rotation::Half, _) => true,
+            (Rotation::ThreeQuarters, Orientation::Horizontal) => false,
+            (Rotation::ThreeQuarters, Orientation::Vertical) => true,
+        };
+        if swap {
+            std::mem::swap(left, right);
+            *ratio = 1.0 - *ratio;
+        }
+        if rotation != Rotation::Half {
+            *orientation = orientation.toggle();
+        }
+        turn(left, rotation);
+        turn(right, rotation);
+    }
+}
+
+// Horizontal mirrors left and right, Vertical mirrors top and bottom.
+pub fn mirror<T: Clone>(root: &mut Node<T>, orientation: Orientation) {
+    // left and right are the children of vertical separators
+    flip(root, orientation.toggle());
+    resize_children(root);
+}
+
+fn flip<T>(root: &mut Node<T>, orientation: Orientation) {
+    if let NodeType::Separator(o, ratio, left, right) = &mut root.node_type {
+        if *o == orientation {
+            std::mem::swap(left, right);
+            *ratio = 1.0 - *ratio;
+        }
+        flip(left, orientation);
+        flip(right, orientation);
+    }
+}
+
+// Resets every ratio so each window gets the same area, whatever the shape of the tree.
+pub fn balance<T: Clone>(root: &mut Node<T>) {
+    weigh(root);
+    resize_children(root);
+}
+
+// sets the ratios under root by the number of leaves on each side, returns the leaves under root
+fn weigh<T>(root: &mut Node<T>) -> usize {
+    match &mut root.node_type {
+        NodeType::Separator(_, ratio, left, right) => {
+            let left_leaves = weigh(left);
+            let right_leaves = weigh(right);
+            *ratio = left_leaves as f32 / (left_leaves + right_leaves) as f32;
+            left_leaves + right_leaves
+        }
+        _ => 1,
+    }
+}
+
+// Gives every child of the container the same share of its space. Only the container's own
+// separators change, nested containers of the other orientation keep their ratios.
+pub fn equalize<T: Clone>(root: &mut Node<T>) {
+    if let NodeType::Separator(orientation, ..) = root.node_type {
+        spread(root, orientation);
+    }
+    resize_children(root);
+}
+
+fn spread<T>(root: &mut Node<T>, orientation: Orientation) {
+    let count = children_along(root, orientation);
+    if let NodeType::Separator(o, ratio, left, right) = &mut root.node_type {
+        if *o == orientation {
+            *ratio = children_along(left, orientation) as f32 / count as f32;
+            spread(left, orientation);
+            spread(right, orientation);
+        }
+    }
+}
+
+// Child indices (0 for left or top, 1 for right or bottom) leading from root to the window.
+pub fn find_path<T: PartialEq>(root: &Node<T>, window: &T) -> Option<Vec<usize>> {
+    match &root.node_type {
+        NodeType::Window(w) if w == window => Some(Vec::new()),
+        NodeType::Separator(_, _, left, right) => [left, right].iter().enumerate().find_map(|(i, child)| {
+            find_path(child, window).map(|mut path| {
+                path.insert(0, i);
+                path
+            })
+        }),
+        _ => None,
+    }
+}
+
+pub fn node_at<'a, T>(root: &'a Node<T>, path: &[usize]) -> Option<&'a Node<T>> {
+    match path.split_first() {
+        None => Some(root),
+        Some((i, rest)) => match &root.node_type {
+            NodeType::Separator(_, _, left, right) => node_at(if *i == 0 { left } else { right }, rest),
+            _ => None,
+        },
+    }
+}
+
+pub fn node_at_mut<'a, T>(root: &'a mut Node<T>, path: &[usize]) -> Option<&'a mut Node<T>> {
+    match path.split_first() {
+        None => Some(root),
+        Some((i, rest)) => match &mut root.node_type {
+            NodeType::Separator(_, _, left, right) => node_at_mut(if *i == 0 { left } else { right }, rest),
+            _ => None,
+        },
+    }
+}
+
+// The container the window is in: the outermost separator of the run of separators with the
+// same orientation as the one directly above the window.
+pub fn find_container<T: PartialEq>(root: &mut Node<T>, window: T) -> Option<&mut Node<T>> {
+    let path = find_path(root, &window)?;
+    let parent = path.len().checked_sub(1)?;
+
+    let orientations: Vec<_> = (0..=parent)
+        .filter_map(|depth| match node_at(root, &path[..depth]).map(|node| &node.node_type) {
+            Some(NodeType::Separator(orientation, ..)) => Some(*orientation),
+            _ => None,
+        })
+        .collect();
+    let start = orientations
+        .iter()
+        .rposition(|orientation| *orientation != orientations[parent])
+        .map_or(0, |i| i + 1);
+
+    node_at_mut(root, &path[..start])
+}
+
+// Takes the node at path out of the tree. Its sibling takes over the parent's space, taking the
+// root leaves the tree empty.
+pub fn take<T: Clone>(root: &mut Node<T>, path: &[usize]) -> Option<Node<T>> {
+    let (last, parent_path) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            let node_type = std::mem::replace(&mut root.node_type, NodeType::Empty);
+            return Some(Node { node_type, dim: root.dim.clone() });
+        }
+    };
+
+    let parent = node_at_mut(root, parent_path)?;
+    if !matches!(parent.node_type, NodeType::Separator(..)) {
+        return None;
+    }
+    if let NodeType::Separator(_, _, left, right) = std::mem::replace(&mut parent.node_type, NodeType::Empty) {
+        let (taken, kept) = if *last == 0 { (left, right) } else { (right, left) };
+        parent.node_type = kept.node_type;
+        resize_children(parent);
+        return Some(*taken);
+    }
+    None
+}
+
+// Moves the node at path one step in direction: out of the nearest container it can leave that
+// way, to sit right next to the boundary it crossed. A node that is a whole side of that
+// container swaps places with the other side instead. Returns the node's new path.
+pub fn move_node<T: Clone>(root: &mut Node<T>, path: &[usize], direction: Direction) -> Option<Vec<usize>> {
+    let backwards = matches!(direction, Direction::Left | Direction::Up);
+    let from_side = if backwards { 1 } else { 0 };
+    let depth = (0..path.len()).rev().find(|depth| {
+        path[*depth] == from_side
+            && matches!(
+                node_at(root, &path[..*depth]).map(|node| &node.node_type),
+                Some(NodeType::Separator(orientation, ..)) if *orientation == direction.orientation()
+            )
+    })?;
+
+    let container = node_at_mut(root, &path[..depth])?;
+    let mut new_path = path[..depth].to_vec();
+    if let NodeType::Separator(_, ratio, left, right) = &mut container.node_type {
+        let (from, to) = if backwards { (right, left) } else { (left, right) };
+        if depth + 1 == path.len() {
+            std::mem::swap(from, to);
+            *ratio = 1.0 - *ratio;
+            new_path.push(1 - from_side);
+        } else {
+            let node = take(from, &path[depth + 1..])?;
+            let side = if backwards { Direction::Right } else { Direction::Left };
+            split(to, side, DEFAULT_RATIO, node);
+            new_path.extend([1 - from_side, from_side]);
+        }
+    }
+    resize_children(container);
+    Some(new_path)
+}
+
+// Gives the node at path more (or less, for a negative change) of its parent's space.
+pub fn resize_node<T: Clone>(root: &mut Node<T>, path: &[usize], change: f32) {
+    let (last, parent_path) = match path.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    if let Some(parent) = node_at_mut(root, parent_path) {
+        if let NodeType::Separator(_, ratio, _, _) = &mut parent.node_type {
+            let change = if *last == 0 { change } else { -change };
+            *ratio = (*ratio + change).clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        }
+        resize_children(parent);
+    }
+}
+
+// Path of the separator whose boundary runs through the point, allowing it to be off by up to
+// tolerance pixels. The boundary is the gap between the end of the first child and the start
+// of the second.
+pub fn separator_at<T>(root: &Node<T>, x: i32, y: i32, tolerance: i32) -> Option<Vec<usize>> {
+    let (x_end, y_end) = (root.dim.x.0 + root.dim.x.1, root.dim.y.0 + root.dim.y.1);
+    if x < root.dim.x.0 - tolerance || x > x_end + tolerance || y < root.dim.y.0 - tolerance || y > y_end + tolerance {
+        return None;
+    }
+
+    match &root.node_type {
+        NodeType::Separator(orientation, _, left, right) => {
+            let (point, end) = match orientation {
+                Orientation::Vertical => (x, left.dim.x.0 + left.dim.x.1),
+                Orientation::Horizontal => (y, left.dim.y.0 + left.dim.y.1),
+            };
+            if point >= end - tolerance && point <= end + 1 + tolerance {
+                return Some(Vec::new());
+            }
+            [left, right].iter().enumerate().find_map(|(i, child)| {
+                separator_at(child, x, y, tolerance).map(|mut path| {
+                    path.insert(0, i);
+                    path
+                })
+            })
+        }
+        _ => None,
+    }
+}
+
+// Moves the separator's boundary so its first child ends at pos, within the ratio limits.
+pub fn drag_separator<T: Clone>(root: &mut Node<T>, pos: i32) {
+    let dim = root.dim.clone();
+    if let NodeType::Separator(orientation, ratio, _, _) = &mut root.node_type {
+        let (start, length) = match orientation {
+            Orientation::Vertical => dim.x,
+            Orientation::Horizontal => dim.y,
+        };
+        if length > 0 {
+            *ratio = ((pos - start) as f32 / length as f32).clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        }
+    }
+    resize_children(root);
+}
+
+// Follows a window being resized from old to new by dragging the separators under each edge
+// that moved along with it. Returns false if the window wasn't resized, only moved.
+pub fn drag_edges<T: Clone>(root: &mut Node<T>, old: &Dimensions, new: &Dimensions, tolerance: i32) -> bool {
+    if old.x.1 == new.x.1 && old.y.1 == new.y.1 {
+        return false;
+    }
+
+    let (middle_x, middle_y) = (old.x.0 + old.x.1 / 2, old.y.0 + old.y.1 / 2);
+    // the point on each edge that moved, and where the first child of its separator ends now
+    let edges = [
+        (old.x.0 != new.x.0, (old.x.0, middle_y), new.x.0 - 1),
+        (old.x.0 + old.x.1 != new.x.0 + new.x.1, (old.x.0 + old.x.1, middle_y), new.x.0 + new.x.1),
+        (old.y.0 != new.y.0, (middle_x, old.y.0), new.y.0 - 1),
+        (old.y.0 + old.y.1 != new.y.0 + new.y.1, (middle_x, old.y.0 + old.y.1), new.y.0 + new.y.1),
+    ];
+    // find every separator before moving any, the hit test needs the layout as it was
+    let drags: Vec<_> = edges
+        .iter()
+        .filter(|(moved, _, _)| *moved)
+        .filter_map(|(_, (x, y), pos)| separator_at(root, *x, *y, tolerance).map(|path| (path, *pos)))
+        .collect();
+
+    for (path, pos) in &drags {
+        if let Some(separator) = node_at_mut(root, path) {
+            drag_separator(separator, *pos);
+        }
+    }
+    !drags.is_empty()
+}
+
+// The window whose rect the point is in.
+pub fn window_at<T: Copy>(root: &Node<T>, x: i32, y: i32) -> Option<T> {
+    let dim = &root.dim;
+    if x < dim.x.0 || x > dim.x.0 + dim.x.1 || y < dim.y.0 || y > dim.y.0 + dim.y.1 {
+        return None;
+    }
+
+    match &root.node_type {
+        NodeType::Window(window) => Some(*window),
+        NodeType::Separator(_, _, left, right) => window_at(left, x, y).or_else(|| window_at(right, x, y)),
+        _ => None,
+    }
+}
+
+// The middle half of the rect each way is the center, the rest is split along the diagonals
+// into the four edges.
+pub fn drop_zone(dim: &Dimensions, x: i32, y: i32) -> DropZone {
+    let dx = (x - dim.x.0) as f32 / dim.x.1.max(1) as f32 - 0.5;
+    let dy = (y - dim.y.0) as f32 / dim.y.1.max(1) as f32 - 0.5;
+
+    if dx.abs() <= 0.25 && dy.abs() <= 0.25 {
+        DropZone::Center
+    } else if dx.abs() >= dy.abs() {
+        DropZone::Edge(if dx < 0.0 { Direction::Left } else { Direction::Right })
+    } else {
+        DropZone::Edge(if dy < 0.0 { Direction::Up } else { Direction::Down })
+    }
+}
+
+// Swaps the places of two windows in the tree.
+pub fn swap_windows<T: Copy + PartialEq>(root: &mut Node<T>, a: T, b: T) {
+    match &mut root.node_type {
+        NodeType::Window(window) if *window == a => *window = b,
+        NodeType::Window(window) if *window == b => *window = a,
+        NodeType::Separator(_, _, left, right) => {
+            swap_windows(left, a, b);
+            swap_windows(right, a, b);
+        }
+        _ => (),
+    }
+}
+
+// Carries out dropping window on target: swapped with it in the center, or taken out of its
+// place and split off the target on an edge. Returns false if either isn't in the tree.
+pub fn drop_window<T: Copy + PartialEq>(root: &mut Node<T>, window: T, target: T, zone: DropZone) -> bool {
+    if window == target || find_path(root, &window).is_none() || find_path(root, &target).is_none() {
+        return false;
+    }
+
+    match zone {
+        DropZone::Center => swap_windows(root, window, target),
+        DropZone::Edge(direction) => {
+            let node = find_path(root, &window).and_then(|path| take(root, &path));
+            if let (Some(node), Some(target)) = (node, find_node(root, target)) {
+                split(target, direction, DEFAULT_RATIO, node);
+            }
+        }
+    }
+    true
+}
+
+pub fn spot<T: PartialEq>(root: &Node<T>, window: &T) -> Option<Spot> {
+    let path = find_path(root, window)?;
+    let (orientation, ratio) = match path.split_last() {
+        Some((last, parent_path)) => match node_at(root, parent_path).map(|parent| &parent.node_type) {
+            Some(NodeType::Separator(orientation, ratio, ..)) => {
+                (*orientation, if *last == 0 { *ratio } else { 1.0 - *ratio })
+            }
+            _ => return None,
+        },
+        None => (Orientation::Horizontal, DEFAULT_RATIO),
+    };
+    Some(Spot { path, orientation, ratio })
+}
+
+// Puts node back at spot. If the tree has changed since, it's split off the node that is as
+// far down the old path as the tree still goes.
+pub fn put_back<T: Clone>(root: &mut Node<T>, spot: &Spot, node: Node<T>) {
+    let (last, parent_path) = match spot.path.split_last() {
+        Some(split) => split,
+        None => {
+            split(root, Direction::after(spot.orientation), spot.ratio, node);
+            return;
+        }
+    };
+
+    let depth = (0..=parent_path.len())
+        .rev()
+        .find(|depth| node_at(root, &parent_path[..*depth]).is_some())
+        .unwrap_or(0);
+    if let Some(target) = node_at_mut(root, &parent_path[..depth]) {
+        let direction = if *last == 0 {
+            Direction::before(spot.orientation)
+        } else {
+            Direction::after(spot.orientation)
+        };
+        split(target, direction, spot.ratio, node);
     }
 }
 
@@ -134,15 +905,140 @@ pub fn find_node<T: Clone + PartialEq>(root: &mut Node<T>, window: T) -> Option<
         }
     }
 
-    if let NodeType::Separator(_, left_win, right_win) = &mut root.node_type {
+    if let NodeType::Separator(_, _, left_win, right_win) = &mut root.node_type {
         let mut node = find_node::<T>(left_win, window.clone());
-        if let None = &mut node {
+        if node.is_none() {
             node = find_node::<T>(right_win, window.clone());
         }
         return node;
     }
 
-    return None;
+    None
+}
+
+// The separator directly above the window.
+pub fn find_parent<T: Clone + PartialEq>(root: &mut Node<T>, window: T) -> Option<&mut Node<T>> {
+    let is_parent = match &root.node_type {
+        NodeType::Separator(_, _, left, right) => [left, right]
+            .iter()
+            .any(|child| matches!(&child.node_type, NodeType::Window(w) if *w == window)),
+        _ => return None,
+    };
+    if is_parent {
+        return Some(root);
+    }
+
+    if let NodeType::Separator(_, _, left, right) = &mut root.node_type {
+        let mut node = find_parent(left, window.clone());
+        if node.is_none() {
+            node = find_parent(right, window);
+        }
+        return node;
+    }
+
+    None
+}
+
+// The first window on the other side of the window's parent, which is what gets the window's
+// space when it's untiled.
+pub fn sibling<T: Clone + PartialEq>(root: &mut Node<T>, window: T) -> Option<T> {
+    let parent = find_parent(root, window.clone())?;
+    match &parent.node_type {
+        NodeType::Separator(_, _, left, right) => {
+            let other = if matches!(&left.node_type, NodeType::Window(w) if *w == window) { right } else { left };
+            windows(other).into_iter().next()
+        }
+        _ => None,
+    }
+}
+
+// every window under root, in tree order
+pub fn windows<T: Clone>(root: &Node<T>) -> Vec<T> {
+    match &root.node_type {
+        NodeType::Window(window) => vec![window.clone()],
+        NodeType::Separator(_, _, left, right) => {
+            let mut found = windows(left);
+            found.extend(windows(right));
+            found
+        }
+        _ => Vec::new(),
+    }
+}
+
+// slots of every placeholder under root, in tree order
+pub fn placeholders<T>(root: &Node<T>) -> Vec<usize> {
+    match &root.node_type {
+        NodeType::Placeholder(slot) => vec![*slot],
+        NodeType::Separator(_, _, left, right) => {
+            let mut slots = placeholders(left);
+            slots.extend(placeholders(right));
+            slots
+        }
+        _ => Vec::new(),
+    }
+}
+
+pub fn find_placeholder<T>(root: &mut Node<T>, slot: usize) -> Option<&mut Node<T>> {
+    if let NodeType::Placeholder(s) = &root.node_type {
+        if *s == slot {
+            return Some(root);
+        } else {
+            return None;
+        }
+    }
+
+    if let NodeType::Separator(_, _, left, right) = &mut root.node_type {
+        let mut node = find_placeholder(left, slot);
+        if node.is_none() {
+            node = find_placeholder(right, slot);
+        }
+        return node;
+    }
+
+    None
+}
+
+pub fn fill_placeholder<T>(root: &mut Node<T>, slot: usize, window: T) -> bool {
+    match find_placeholder(root, slot) {
+        Some(node) => {
+            node.node_type = NodeType::Window(window);
+            true
+        }
+        None => false,
+    }
+}
+
+// Gives every placeholder under root a new slot.
+pub fn renumber_placeholders<T>(root: &mut Node<T>, renumber: &mut dyn FnMut(usize) -> usize) {
+    match &mut root.node_type {
+        NodeType::Placeholder(slot) => *slot = renumber(*slot),
+        NodeType::Separator(_, _, left, right) => {
+            renumber_placeholders(left, renumber);
+            renumber_placeholders(right, renumber);
+        }
+        _ => (),
+    }
+}
+
+pub fn remove_placeholders<T: Clone>(root: &mut Node<T>) {
+    if let NodeType::Placeholder(_) = root.node_type {
+        root.node_type = NodeType::Empty;
+        return;
+    }
+
+    if let NodeType::Separator(_, _, left, right) = &mut root.node_type {
+        remove_placeholders(left);
+        remove_placeholders(right);
+
+        // a separator with an empty side gives all of its space to the other one
+        if let NodeType::Empty = left.node_type {
+            root.node_type = right.node_type.clone();
+            resize_children(root);
+        } else if let NodeType::Empty = right.node_type {
+            root.node_type = left.node_type.clone();
+            resize_children(root);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -156,7 +1052,7 @@ mod test {
             y: (0, 1080)
         };
 
-        let (left_dim, right_dim) = tile_vertical(&base_dim);
+        let (left_dim, right_dim) = tile_vertical(&base_dim, DEFAULT_RATIO);
 
         assert_eq!((0, 960), left_dim.x);
         assert_eq!((0, 1080), left_dim.y);
@@ -171,7 +1067,7 @@ mod test {
             y: (0, 1080)
         };
 
-        let (top_dim, bot_dim) = tile_horizontal(&base_dim);
+        let (top_dim, bot_dim) = tile_horizontal(&base_dim, DEFAULT_RATIO);
 
         assert_eq!((0, 1920), top_dim.x);
         assert_eq!((0, 540), top_dim.y);
@@ -197,7 +1093,7 @@ mod test {
         };
 
         let mut root: Node<i32> = Node {
-            node_type: NodeType::Separator(Orientation::Vertical, Box::new(left_leaf), Box::new(right_leaf)),
+            node_type: NodeType::Separator(Orientation::Vertical, DEFAULT_RATIO, Box::new(left_leaf), Box::new(right_leaf)),
             dim: Dimensions {
                 x: (0, 1920),
                 y: (0, 1080)
@@ -230,7 +1126,7 @@ mod test {
         };
 
         let mut root: Node<i32> = Node {
-            node_type: NodeType::Separator(Orientation::Vertical, Box::new(left_leaf), Box::new(right_leaf)),
+            node_type: NodeType::Separator(Orientation::Vertical, DEFAULT_RATIO, Box::new(left_leaf), Box::new(right_leaf)),
             dim: Dimensions {
                 x: (0, 1920),
                 y: (0, 1080)
@@ -257,7 +1153,7 @@ mod test {
 
         tile(&mut root, Orientation::Vertical, 2);
 
-        if let NodeType::Separator(Orientation::Vertical, left, right) = root.node_type {
+        if let NodeType::Separator(Orientation::Vertical, _, left, right) = root.node_type {
             if let NodeType::Window(val) = left.node_type {
                 assert_eq!(val, 1);
             } else {
@@ -286,7 +1182,7 @@ mod test {
 
         tile(&mut root, Orientation::Vertical, 2);
 
-        if let NodeType::Separator(Orientation::Vertical, left, right) = root.node_type {
+        if let NodeType::Separator(Orientation::Vertical, _, left, right) = root.node_type {
             let left_dim = left.dim;
             assert_eq!(left_dim, Dimensions {
                 x: (0, 960),
@@ -315,7 +1211,7 @@ mod test {
 
         tile(&mut root, Orientation::Horizontal, 2);
 
-        if let NodeType::Separator(Orientation::Horizontal, left, right) = root.node_type {
+        if let NodeType::Separator(Orientation::Horizontal, _, left, right) = root.node_type {
             if let NodeType::Window(val) = left.node_type {
                 assert_eq!(val, 1);
             } else {
@@ -344,7 +1240,7 @@ mod test {
 
         tile(&mut root, Orientation::Horizontal, 2);
 
-        if let NodeType::Separator(Orientation::Horizontal, left, right) = root.node_type {
+        if let NodeType::Separator(Orientation::Horizontal, _, left, right) = root.node_type {
             let left_dim = left.dim;
             assert_eq!(left_dim, Dimensions {
                 x: (0, 1920),
@@ -379,7 +1275,7 @@ mod test {
         };
 
         let mut root: Node<i32> = Node {
-            node_type: NodeType::Separator(Orientation::Vertical, Box::new(left_child), Box::new(right_child)),
+            node_type: NodeType::Separator(Orientation::Vertical, DEFAULT_RATIO, Box::new(left_child), Box::new(right_child)),
             dim: Dimensions {
                 x: (0, 1920),
                 y: (0, 1080)
@@ -388,7 +1284,7 @@ mod test {
 
         resize_children(&mut root);
 
-        if let NodeType::Separator(_, left, right) = root.node_type {
+        if let NodeType::Separator(_, _, left, right) = root.node_type {
             assert_eq!(left.dim, Dimensions {
                 x: (0, 960),
                 y: (0, 1080)
@@ -419,7 +1315,7 @@ mod test {
             }
         };
         let mut root: Node<i32> = Node {
-            node_type: NodeType::Separator(Orientation::Horizontal, Box::new(left_child), Box::new(right_child)),
+            node_type: NodeType::Separator(Orientation::Horizontal, DEFAULT_RATIO, Box::new(left_child), Box::new(right_child)),
             dim: Dimensions {
                 x: (0, 1920),
                 y: (0, 1080)
@@ -455,7 +1351,7 @@ mod test {
             }
         };
         let mut root: Node<i32> = Node {
-            node_type: NodeType::Separator(Orientation::Horizontal, Box::new(left_child), Box::new(right_child)),
+            node_type: NodeType::Separator(Orientation::Horizontal, DEFAULT_RATIO, Box::new(left_child), Box::new(right_child)),
             dim: Dimensions {
                 x: (0, 1920),
                 y: (0, 1080)
@@ -465,4 +1361,502 @@ mod test {
         let result = find_node(&mut root, 3);
         assert!(result.is_none());
     }
+
+    fn screen() -> Dimensions {
+        Dimensions {
+            x: (0, 1920),
+            y: (0, 1080)
+        }
+    }
+
+    #[test]
+    fn split_should_put_new_node_on_given_side_with_given_share() {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        let new_node = Node {
+            node_type: NodeType::Window(2),
+            dim: screen()
+        };
+
+        split(&mut root, Direction::Left, 0.25, new_node);
+
+        if let NodeType::Separator(Orientation::Vertical, _, left, right) = root.node_type {
+            assert_eq!(NodeType::Window(2), left.node_type);
+            assert_eq!(Dimensions { x: (0, 480), y: (0, 1080) }, left.dim);
+            assert_eq!(NodeType::Window(1), right.node_type);
+            assert_eq!(Dimensions { x: (481, 1440), y: (0, 1080) }, right.dim);
+        } else {
+            panic!("Root is not a vertical separator");
+        }
+    }
+
+    #[test]
+    fn insert_parent_should_give_new_window_an_even_share_of_the_container() {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        tile(&mut root, Orientation::Vertical, 2);
+        let new_node = Node {
+            node_type: NodeType::Window(3),
+            dim: screen()
+        };
+
+        insert(&mut root, Some(2), Insertion::Parent, Orientation::Horizontal, new_node);
+
+        assert_eq!(3, children_along(&root, Orientation::Vertical));
+        if let NodeType::Separator(Orientation::Vertical, _, left, right) = &root.node_type {
+            assert_eq!(Dimensions { x: (0, 1280), y: (0, 1080) }, left.dim);
+            assert_eq!(NodeType::Window(3), right.node_type);
+            assert_eq!(Dimensions { x: (1281, 640), y: (0, 1080) }, right.dim);
+        } else {
+            panic!("Root is not a vertical separator");
+        }
+    }
+
+    #[test]
+    fn insert_root_should_add_new_window_next_to_whole_tree() {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        tile(&mut root, Orientation::Vertical, 2);
+        let new_node = Node {
+            node_type: NodeType::Window(3),
+            dim: screen()
+        };
+
+        insert(&mut root, Some(2), Insertion::Root, Orientation::Horizontal, new_node);
+
+        if let NodeType::Separator(Orientation::Horizontal, _, top, bottom) = &root.node_type {
+            assert!(matches!(top.node_type, NodeType::Separator(Orientation::Vertical, ..)));
+            assert_eq!(NodeType::Window(3), bottom.node_type);
+            assert_eq!(Dimensions { x: (0, 1920), y: (541, 540) }, bottom.dim);
+        } else {
+            panic!("Root is not a horizontal separator");
+        }
+    }
+
+    #[test]
+    fn insert_auto_should_split_along_longer_side() {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        tile(&mut root, Orientation::Vertical, 2);
+        let new_node = Node {
+            node_type: NodeType::Window(3),
+            dim: screen()
+        };
+
+        // window 2 is 960x1080, so it's split top and bottom
+        insert(&mut root, Some(2), Insertion::Auto, Orientation::Vertical, new_node);
+
+        let node = find_node(&mut root, 3).unwrap();
+        assert_eq!(Dimensions { x: (961, 960), y: (541, 540) }, node.dim);
+    }
+
+    // 1 on the left, 2 above 3 on the right
+    fn three_windows() -> Node<i32> {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        tile(&mut root, Orientation::Vertical, 2);
+        let right = find_node(&mut root, 2).unwrap();
+        tile(right, Orientation::Horizontal, 3);
+        root
+    }
+
+    #[test]
+    fn toggle_orientation_should_only_change_given_separator() {
+        let mut root = three_windows();
+
+        toggle_orientation(&mut root);
+
+        if let NodeType::Separator(Orientation::Horizontal, _, top, bottom) = &root.node_type {
+            assert_eq!(Dimensions { x: (0, 1920), y: (0, 540) }, top.dim);
+            assert!(matches!(bottom.node_type, NodeType::Separator(Orientation::Horizontal, ..)));
+            assert_eq!(Dimensions { x: (0, 1920), y: (812, 270) }, find_node(&mut root, 3).unwrap().dim);
+        } else {
+            panic!("Root is not a horizontal separator");
+        }
+    }
+
+    #[test]
+    fn rotate_quarter_should_turn_layout_clockwise() {
+        let mut root = three_windows();
+
+        rotate(&mut root, Rotation::Quarter);
+
+        // 1 on top, 3 left of 2 at the bottom
+        assert_eq!(Dimensions { x: (0, 1920), y: (0, 540) }, find_node(&mut root, 1).unwrap().dim);
+        assert_eq!(Dimensions { x: (0, 960), y: (541, 540) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(Dimensions { x: (961, 960), y: (541, 540) }, find_node(&mut root, 2).unwrap().dim);
+    }
+
+    #[test]
+    fn rotate_half_should_swap_every_separator() {
+        let mut root = three_windows();
+
+        rotate(&mut root, Rotation::Half);
+
+        // 3 above 2 on the left, 1 on the right
+        assert_eq!(Dimensions { x: (0, 960), y: (0, 540) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(Dimensions { x: (0, 960), y: (541, 540) }, find_node(&mut root, 2).unwrap().dim);
+        assert_eq!(Dimensions { x: (961, 960), y: (0, 1080) }, find_node(&mut root, 1).unwrap().dim);
+    }
+
+    #[test]
+    fn rotate_full_turn_should_restore_layout() {
+        let mut root = three_windows();
+        if let NodeType::Separator(_, ratio, _, _) = &mut root.node_type {
+            *ratio = 0.25;
+        }
+        resize_children(&mut root);
+        let original = root.clone();
+
+        rotate(&mut root, Rotation::Quarter);
+        rotate(&mut root, Rotation::Half);
+        rotate(&mut root, Rotation::Quarter);
+        assert_eq!(original, root);
+
+        rotate(&mut root, Rotation::ThreeQuarters);
+        rotate(&mut root, Rotation::Quarter);
+        assert_eq!(original, root);
+    }
+
+    #[test]
+    fn mirror_should_only_swap_along_given_axis() {
+        let mut root = three_windows();
+
+        mirror(&mut root, Orientation::Horizontal);
+
+        // 2 above 3 on the left, 1 on the right
+        assert_eq!(Dimensions { x: (0, 960), y: (0, 540) }, find_node(&mut root, 2).unwrap().dim);
+        assert_eq!(Dimensions { x: (0, 960), y: (541, 540) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(Dimensions { x: (961, 960), y: (0, 1080) }, find_node(&mut root, 1).unwrap().dim);
+
+        mirror(&mut root, Orientation::Vertical);
+
+        assert_eq!(Dimensions { x: (0, 960), y: (0, 540) }, find_node(&mut root, 3).unwrap().dim);
+    }
+
+    fn hints(min: (i32, i32), max: (i32, i32)) -> SizeHints {
+        SizeHints { min, max }
+    }
+
+    #[test]
+    fn split_position_should_keep_ratio_within_limits() {
+        let any = (0, i32::MAX);
+
+        assert_eq!(960, split_position(1920, 0.5, any, any));
+        // the right side refuses to get narrower than 1200
+        assert_eq!(720, split_position(1920, 0.5, any, (1200, i32::MAX)));
+        // the left side can't get wider than 400, the right one takes the rest
+        assert_eq!(400, split_position(1920, 0.5, (0, 400), any));
+        // already within the limits
+        assert_eq!(480, split_position(1920, 0.25, (300, 1000), (800, i32::MAX)));
+    }
+
+    #[test]
+    fn split_position_should_put_minimums_before_maximums() {
+        // both sides would rather be smaller than there's room for, the ratio decides
+        assert_eq!(960, split_position(1920, 0.5, (0, 500), (0, 500)));
+        // the left side wants at most 500 but the right needs 1600
+        assert_eq!(320, split_position(1920, 0.5, (0, 500), (1600, i32::MAX)));
+    }
+
+    #[test]
+    fn split_position_should_share_by_minimums_when_they_do_not_fit() {
+        assert_eq!(640, split_position(1920, 0.5, (1000, i32::MAX), (2000, i32::MAX)));
+        assert_eq!(0, split_position(0, 0.5, (100, i32::MAX), (100, i32::MAX)));
+    }
+
+    #[test]
+    fn fit_should_let_window_with_minimum_take_room_from_neighbours() {
+        // 1 | 2 | 3, with 2 at least 800 wide and then 1 at least 1100
+        let mut root: Node<i32> = Node { node_type: NodeType::Window(1), dim: screen() };
+        tile(&mut root, Orientation::Vertical, 2);
+        tile(find_node(&mut root, 2).unwrap(), Orientation::Vertical, 3);
+        let min_width = |width| hints((width, 0), (i32::MAX, i32::MAX));
+
+        let overflowing = fit(&mut root, &|window| match window {
+            2 => min_width(800),
+            _ => SizeHints::default(),
+        });
+
+        assert!(overflowing.is_empty());
+        assert_eq!((0, 960), find_node(&mut root, 1).unwrap().dim.x);
+        assert_eq!((961, 800), find_node(&mut root, 2).unwrap().dim.x);
+        assert_eq!((1762, 160), find_node(&mut root, 3).unwrap().dim.x);
+
+        let overflowing = fit(&mut root, &|window| match window {
+            1 => min_width(1100),
+            2 => min_width(800),
+            _ => SizeHints::default(),
+        });
+
+        assert!(overflowing.is_empty());
+        assert_eq!((0, 1100), find_node(&mut root, 1).unwrap().dim.x);
+        assert_eq!((1101, 800), find_node(&mut root, 2).unwrap().dim.x);
+        assert_eq!((1902, 20), find_node(&mut root, 3).unwrap().dim.x);
+    }
+
+    #[test]
+    fn fit_should_add_up_minimums_of_nested_containers() {
+        // 1 on the left, 2 above 3 on the right, 2 and 3 both at least 600 wide
+        let mut root: Node<i32> = Node { node_type: NodeType::Window(1), dim: screen() };
+        tile(&mut root, Orientation::Vertical, 2);
+        tile(find_node(&mut root, 2).unwrap(), Orientation::Horizontal, 3);
+        let wide = |window: &i32| match window {
+            1 => SizeHints::default(),
+            _ => hints((1200, 400), (i32::MAX, i32::MAX)),
+        };
+
+        let overflowing = fit(&mut root, &wide);
+
+        assert!(overflowing.is_empty());
+        assert_eq!((0, 720), find_node(&mut root, 1).unwrap().dim.x);
+        assert_eq!((721, 1200), find_node(&mut root, 3).unwrap().dim.x);
+
+        // two 700 high windows can't be stacked in 1080
+        let tall = |window: &i32| match window {
+            1 => SizeHints::default(),
+            _ => hints((0, 700), (i32::MAX, i32::MAX)),
+        };
+        let overflowing = fit(&mut root, &tall);
+
+        assert_eq!(vec![2, 3], overflowing);
+        assert_eq!((0, 540), find_node(&mut root, 2).unwrap().dim.y);
+    }
+
+    #[test]
+    fn fit_should_match_resize_children_without_hints() {
+        let mut root: Node<i32> = Node { node_type: NodeType::Window(1), dim: screen() };
+        tile(&mut root, Orientation::Vertical, 2);
+        tile(find_node(&mut root, 2).unwrap(), Orientation::Horizontal, 3);
+        resize_node(&mut root, &[1, 0], 0.1);
+        let mut expected = root.clone();
+        resize_children(&mut expected);
+
+        assert!(fit(&mut root, &|_| SizeHints::default()).is_empty());
+
+        assert_eq!(expected, root);
+    }
+
+    #[test]
+    fn changed_should_only_return_moved_and_new_windows() {
+        let dim = |x| Dimensions { x: (x, 100), y: (0, 100) };
+        let old = vec![(1, dim(0)), (2, dim(100)), (3, dim(200))];
+        let new = vec![(1, dim(0)), (3, dim(100)), (4, dim(200))];
+
+        assert_eq!(vec![(3, dim(100)), (4, dim(200))], changed(&old, &new));
+        assert!(changed(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn balance_should_give_every_window_the_same_area() {
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        for window in 2..=4 {
+            let last = find_node(&mut root, window - 1).unwrap();
+            tile(last, Orientation::Vertical, window);
+        }
+
+        balance(&mut root);
+
+        for window in 1..=4 {
+            assert_eq!(480, find_node(&mut root, window).unwrap().dim.x.1);
+        }
+    }
+
+    #[test]
+    fn equalize_should_only_even_out_focused_container() {
+        // 1 and 2 side by side with 3 to the right of 2, and 4 below all of them
+        let mut root: Node<i32> = Node {
+            node_type: NodeType::Window(1),
+            dim: screen()
+        };
+        tile(&mut root, Orientation::Horizontal, 4);
+        tile(find_node(&mut root, 1).unwrap(), Orientation::Vertical, 2);
+        tile(find_node(&mut root, 2).unwrap(), Orientation::Vertical, 3);
+
+        let container = find_container(&mut root, 3).unwrap();
+        equalize(container);
+
+        assert_eq!(Dimensions { x: (0, 640), y: (0, 540) }, find_node(&mut root, 1).unwrap().dim);
+        assert_eq!(Dimensions { x: (641, 640), y: (0, 540) }, find_node(&mut root, 2).unwrap().dim);
+        assert_eq!(Dimensions { x: (1282, 640), y: (0, 540) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(Dimensions { x: (0, 1920), y: (541, 540) }, find_node(&mut root, 4).unwrap().dim);
+    }
+
+    #[test]
+    fn sibling_should_be_first_window_on_other_side_of_parent() {
+        let mut root = three_windows();
+
+        assert_eq!(Some(2), sibling(&mut root, 1));
+        assert_eq!(Some(3), sibling(&mut root, 2));
+        assert_eq!(None, sibling(&mut root, 4));
+        assert_eq!(vec![1, 2, 3], windows(&root));
+    }
+
+    #[test]
+    fn take_should_give_parent_space_to_sibling() {
+        let mut root = three_windows();
+
+        let taken = take(&mut root, &[1]).unwrap();
+
+        assert_eq!(vec![2, 3], windows(&taken));
+        assert_eq!(NodeType::Window(1), root.node_type);
+        assert_eq!(screen(), root.dim);
+    }
+
+    #[test]
+    fn move_node_should_swap_whole_side_with_other_side() {
+        let mut root = three_windows();
+
+        let path = move_node(&mut root, &[1], Direction::Left);
+
+        assert_eq!(Some(vec![0]), path);
+        assert_eq!(vec![2, 3, 1], windows(&root));
+        assert_eq!(Dimensions { x: (961, 960), y: (0, 1080) }, find_node(&mut root, 1).unwrap().dim);
+    }
+
+    #[test]
+    fn move_node_should_cross_into_neighbouring_side() {
+        let mut root = three_windows();
+
+        // 3 leaves the right side and ends up to the right of 1
+        let path = move_node(&mut root, &[1, 1], Direction::Left);
+
+        assert_eq!(Some(vec![0, 1]), path);
+        assert_eq!(vec![1, 3, 2], windows(&root));
+        assert_eq!(Dimensions { x: (961, 960), y: (0, 1080) }, find_node(&mut root, 2).unwrap().dim);
+        assert_eq!(Dimensions { x: (481, 480), y: (0, 1080) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(None, move_node(&mut root, &[1], Direction::Right));
+    }
+
+    #[test]
+    fn resize_node_should_move_parent_ratio_within_limits() {
+        let mut root = three_windows();
+
+        resize_node(&mut root, &[1], 0.25);
+        assert_eq!(Dimensions { x: (0, 480), y: (0, 1080) }, find_node(&mut root, 1).unwrap().dim);
+
+        resize_node(&mut root, &[0], -1.0);
+        assert_eq!(96, find_node(&mut root, 1).unwrap().dim.x.1);
+    }
+
+    #[test]
+    fn separator_at_should_find_boundary_between_two_rects() {
+        let root = three_windows();
+
+        assert_eq!(Some(vec![]), separator_at(&root, 961, 100, 0));
+        assert_eq!(Some(vec![1]), separator_at(&root, 1500, 541, 0));
+        assert_eq!(Some(vec![1]), separator_at(&root, 1500, 545, 4));
+        assert_eq!(None, separator_at(&root, 500, 541, 4));
+        assert_eq!(None, separator_at(&root, 3000, 100, 4));
+    }
+
+    #[test]
+    fn drag_edges_should_move_separator_under_dragged_edge() {
+        let mut root = three_windows();
+        let old = Dimensions { x: (961, 960), y: (541, 540) };
+
+        // 3's left edge dragged to the left, its top edge dragged down
+        let new = Dimensions { x: (481, 1440), y: (811, 270) };
+        assert!(drag_edges(&mut root, &old, &new, 2));
+
+        assert_eq!(Dimensions { x: (0, 480), y: (0, 1080) }, find_node(&mut root, 1).unwrap().dim);
+        assert_eq!(new, find_node(&mut root, 3).unwrap().dim);
+    }
+
+    #[test]
+    fn drag_edges_should_ignore_moves_and_screen_edges() {
+        let mut root = three_windows();
+        let old = Dimensions { x: (961, 960), y: (541, 540) };
+
+        assert!(!drag_edges(&mut root, &old, &Dimensions { x: (900, 960), y: (500, 540) }, 2));
+        assert!(!drag_edges(&mut root, &old, &Dimensions { x: (961, 1000), y: (541, 600) }, 2));
+        assert_eq!(three_windows(), root);
+    }
+
+    #[test]
+    fn window_at_should_find_window_under_point() {
+        let root = three_windows();
+
+        assert_eq!(Some(1), window_at(&root, 100, 900));
+        assert_eq!(Some(3), window_at(&root, 1500, 900));
+        assert_eq!(None, window_at(&root, 2000, 900));
+    }
+
+    #[test]
+    fn drop_zone_should_split_rect_into_center_and_edges() {
+        let dim = Dimensions { x: (100, 400), y: (0, 200) };
+
+        assert_eq!(DropZone::Center, drop_zone(&dim, 300, 100));
+        assert_eq!(DropZone::Center, drop_zone(&dim, 200, 50));
+        assert_eq!(DropZone::Edge(Direction::Left), drop_zone(&dim, 120, 90));
+        assert_eq!(DropZone::Edge(Direction::Right), drop_zone(&dim, 480, 150));
+        assert_eq!(DropZone::Edge(Direction::Up), drop_zone(&dim, 300, 10));
+        assert_eq!(DropZone::Edge(Direction::Down), drop_zone(&dim, 200, 190));
+    }
+
+    #[test]
+    fn drop_window_in_center_should_swap_windows() {
+        let mut root = three_windows();
+
+        assert!(drop_window(&mut root, 1, 3, DropZone::Center));
+
+        assert_eq!(vec![3, 2, 1], windows(&root));
+        assert_eq!(Dimensions { x: (0, 960), y: (0, 1080) }, find_node(&mut root, 3).unwrap().dim);
+    }
+
+    #[test]
+    fn drop_window_on_edge_should_split_target() {
+        let mut root = three_windows();
+
+        assert!(drop_window(&mut root, 3, 1, DropZone::Edge(Direction::Down)));
+
+        assert_eq!(vec![1, 3, 2], windows(&root));
+        assert_eq!(Dimensions { x: (0, 960), y: (541, 540) }, find_node(&mut root, 3).unwrap().dim);
+        assert_eq!(Dimensions { x: (961, 960), y: (0, 1080) }, find_node(&mut root, 2).unwrap().dim);
+        assert!(!drop_window(&mut root, 3, 3, DropZone::Center));
+        assert!(!drop_window(&mut root, 3, 7, DropZone::Center));
+    }
+
+    #[test]
+    fn put_back_should_return_window_to_its_spot() {
+        let mut root = three_windows();
+        if let NodeType::Separator(_, ratio, _, _) = &mut root.node_type {
+            *ratio = 0.25;
+        }
+        resize_children(&mut root);
+        let before = root.clone();
+
+        let spot = spot(&root, &1).unwrap();
+        assert_eq!(Spot { path: vec![0], orientation: Orientation::Vertical, ratio: 0.25 }, spot);
+        untile(&mut root, &1);
+        put_back(&mut root, &spot, Node { node_type: NodeType::Window(1), dim: screen() });
+
+        assert_eq!(before, root);
+    }
+
+    #[test]
+    fn put_back_should_split_what_is_left_of_old_path() {
+        let mut root = three_windows();
+        let spot = spot(&root, &2).unwrap();
+        untile(&mut root, &2);
+        untile(&mut root, &3);
+
+        put_back(&mut root, &spot, Node { node_type: NodeType::Window(2), dim: screen() });
+
+        assert_eq!(vec![2, 1], windows(&root));
+        assert_eq!(Dimensions { x: (0, 1920), y: (0, 540) }, find_node(&mut root, 2).unwrap().dim);
+    }
 }
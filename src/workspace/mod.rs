@@ -0,0 +1,259 @@
+use crate::internal::{Criteria, WindowInfo};
+use crate::monitor::{self, Monitor};
+use crate::tile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Where the next window goes, chosen ahead of time. Used up by the next window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preselection<W> {
+    // None preselects the whole workspace
+    pub window: Option<W>,
+    pub direction: tile::Direction,
+    // share of the space the new window gets
+    pub ratio: f32,
+}
+
+// A tiled window shown over the rest of the workspace. It keeps its place in the tree, so
+// the layout comes back as it was once the zoom ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zoom<W> {
+    pub window: W,
+    pub area: ZoomArea,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoomArea {
+    // the space windows are tiled in
+    Workspace,
+    // the whole monitor, covering bars too
+    Monitor,
+}
+
+// Where a minimized window goes back to once it's restored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Place {
+    Tiled(tile::Spot),
+    Floating(tile::Dimensions),
+}
+
+pub struct Workspace<W> {
+    pub name: String,
+    pub root: tile::Node<W>,
+    pub focus: Option<W>,
+    // windows in the order they were focused, most recent first
+    pub history: Vec<W>,
+    pub preselection: Option<Preselection<W>>,
+    // balance the tree whenever windows come and go
+    pub auto_balance: bool,
+    pub zoom: Option<Zoom<W>>,
+    // path of the selected container once a parent of the focused window has been selected,
+    // None while the focused window itself is
+    pub selection: Option<Vec<usize>>,
+    // subtrees taken out of the tree, drawn over it at their own position
+    pub floating: Vec<tile::Node<W>>,
+    // windows taken out of the layout while minimized, with where they were
+    pub minimized: Vec<(W, Place)>,
+    // the monitor the workspace is laid out on now
+    pub monitor: Option<Monitor>,
+    // id of the monitor the workspace belongs on, it goes back there whenever it's connected
+    pub home: Option<String>,
+    // space around tiled windows, in logical pixels
+    pub gaps: i32,
+    // where the last redraw put each window, for checking they stayed there
+    pub placed: Vec<(W, tile::Dimensions)>,
+    // what happens when the windows' minimum sizes don't fit
+    pub overflow: tile::Overflow,
+    // windows the overflow policy floated, with where they were in the tree
+    pub overflowed: Vec<(W, tile::Spot)>,
+    // size limits each window reported, kept until it's created again or moves itself
+    pub hints: Vec<(W, tile::SizeHints)>,
+    // match criteria of every placeholder in the tree, by slot
+    pub slots: HashMap<usize, Criteria>,
+    next_slot: usize,
+}
+
+impl<W> Workspace<W> {
+    pub fn new(name: &str, dim: tile::Dimensions) -> Workspace<W> {
+        Workspace {
+            name: name.to_string(),
+            root: tile::Node {
+                node_type: tile::NodeType::Empty,
+                dim,
+            },
+            focus: None,
+            history: Vec::new(),
+            preselection: None,
+            auto_balance: false,
+            zoom: None,
+            selection: None,
+            floating: Vec::new(),
+            minimized: Vec::new(),
+            monitor: None,
+            home: None,
+            gaps: 0,
+            placed: Vec::new(),
+            overflow: tile::Overflow::Shrink,
+            overflowed: Vec::new(),
+            hints: Vec::new(),
+            slots: HashMap::new(),
+            next_slot: 0,
+        }
+    }
+
+    // the gaps in physical pixels on the workspace's monitor
+    pub fn gap(&self) -> i32 {
+        let scale = self.monitor.as_ref().map_or(1.0, |monitor| monitor.scale);
+        monitor::scaled(self.gaps, scale)
+    }
+
+    pub fn add_slot(&mut self, criteria: Criteria) -> usize {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.slots.insert(slot, criteria);
+        slot
+    }
+
+    // Puts the window into the first placeholder (in tree order) that it matches.
+    pub fn claim_placeholder(&mut self, window: W, info: &WindowInfo) -> bool {
+        let slot = tile::placeholders(&self.root)
+            .into_iter()
+            .find(|slot| self.slots.get(slot).is_some_and(|criteria| criteria.matches(info)));
+
+        match slot {
+            Some(slot) => {
+                self.slots.remove(&slot);
+                tile::fill_placeholder(&mut self.root, slot, window)
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_placeholders(&mut self)
+    where
+        W: Clone,
+    {
+        tile::remove_placeholders(&mut self.root);
+        self.slots.clear();
+    }
+}
+
+impl<W: Copy + PartialEq> Workspace<W> {
+    // every window on the workspace, tiled ones first
+    pub fn windows(&self) -> Vec<W> {
+        let mut windows = tile::windows(&self.root);
+        for node in &self.floating {
+            windows.extend(tile::windows(node));
+        }
+        windows
+    }
+
+    pub fn contains(&self, window: W) -> bool {
+        self.windows().contains(&window)
+    }
+
+    pub fn floating_index(&self, window: W) -> Option<usize> {
+        self.floating.iter().position(|node| tile::windows(node).contains(&window))
+    }
+
+    // The selected container, or the focused window when nothing else is selected.
+    pub fn selected_path(&self) -> Option<Vec<usize>> {
+        self.selection
+            .clone()
+            .or_else(|| self.focus.and_then(|focus| tile::find_path(&self.root, &focus)))
+    }
+
+    // Where the window is in the layout, so it can be put back there later.
+    pub fn place(&self, window: W) -> Option<Place> {
+        match self.floating_index(window) {
+            Some(i) => {
+                let node = &self.floating[i];
+                let node = tile::find_path(node, &window).and_then(|path| tile::node_at(node, &path))?;
+                Some(Place::Floating(node.dim.clone()))
+            }
+            None => tile::spot(&self.root, &window).map(Place::Tiled),
+        }
+    }
+
+    pub fn take_minimized(&mut self, window: W) -> Option<Place> {
+        let i = self.minimized.iter().position(|(w, _)| *w == window)?;
+        Some(self.minimized.remove(i).1)
+    }
+
+    pub fn put_back(&mut self, window: W, place: Place) {
+        let mut node = tile::Node {
+            node_type: tile::NodeType::Window(window),
+            dim: self.root.dim.clone(),
+        };
+        match place {
+            Place::Tiled(spot) => tile::put_back(&mut self.root, &spot, node),
+            Place::Floating(dim) => {
+                node.dim = dim;
+                self.floating.push(node);
+            }
+        }
+    }
+
+    // Takes the window out of the workspace, without redrawing anything.
+    pub fn remove_window(&mut self, window: W) {
+        if self.preselection.is_some_and(|presel| presel.window == Some(window)) {
+            self.preselection = None;
+        }
+        if self.zoom.is_some_and(|zoom| zoom.window == window) {
+            self.zoom = None;
+        }
+
+        let node = match self.floating_index(window) {
+            Some(i) => &mut self.floating[i],
+            None => &mut self.root,
+        };
+        if matches!(node.node_type, tile::NodeType::Window(w) if w == window) {
+            node.node_type = tile::NodeType::Empty;
+        } else {
+            tile::untile(node, &window);
+        }
+        self.floating.retain(|node| !matches!(node.node_type, tile::NodeType::Empty));
+        self.selection = None;
+        self.forget_focus(window);
+    }
+
+    // Puts windows floated for not fitting back where they were, to be tried again now that
+    // something may have made room. Ones that still don't fit get floated again by the redraw.
+    pub fn retile_overflowed(&mut self) {
+        for (window, spot) in std::mem::take(&mut self.overflowed) {
+            let floating = self
+                .floating
+                .iter()
+                .position(|node| node.node_type == tile::NodeType::Window(window));
+            if let Some(i) = floating {
+                self.floating.remove(i);
+                self.put_back(window, Place::Tiled(spot));
+            }
+        }
+    }
+
+    pub fn forget_hints(&mut self, window: W) {
+        self.hints.retain(|(w, _)| *w != window);
+    }
+
+    // Moves the placeholders in a node coming from another workspace over to this one's slots.
+    pub fn adopt_slots(&mut self, node: &mut tile::Node<W>, mut slots: HashMap<usize, Criteria>) {
+        tile::renumber_placeholders(node, &mut |slot| self.add_slot(slots.remove(&slot).unwrap_or_default()));
+    }
+
+    pub fn remember_focus(&mut self, window: W) {
+        self.focus = Some(window);
+        self.history.retain(|w| *w != window);
+        self.history.insert(0, window);
+    }
+
+    // Drops the window from the focus history. If it had focus, the window focused before it
+    // takes over.
+    pub fn forget_focus(&mut self, window: W) {
+        self.history.retain(|w| *w != window);
+        if self.focus == Some(window) {
+            self.focus = self.history.first().copied();
+        }
+    }
+}